@@ -3,33 +3,28 @@ extern crate flacparse;
 use std::env;
 use std::process::exit;
 use std::io;
-use std::fs::File;
-use std::io::{Read, BufRead, BufReader};
+use std::io::Read;
 
 use flacparse::*;
 
-fn analyse_flac_file<R>(f: &mut R) -> Result<(), io::Error>
-where R: Read + BufRead {  
-    let vorbis_comments = parse(f)?;
-
-    println!("Comments: {:?}", vorbis_comments);
-    println!("Title: {}", vorbis_comments.title().unwrap_or_default());
-    println!("Artist: {}", vorbis_comments.artist().unwrap_or_default());
-    println!("Album: {}", vorbis_comments.album().unwrap_or_default());
-    println!("Number: {}", vorbis_comments.tracknumber().unwrap_or_default());
-
-    Ok(())
+fn print_metadata(vorbis_comments: MusicMetaData) {
+    println!("{}", vorbis_comments);
+    println!();
+    for (key, value) in vorbis_comments.iter() {
+        println!("{}={}", key, value);
+    }
 }
 
 fn main() {
     let stdin = io::stdin();
     if let Some(input) = env::args().nth(1) {
-        match input.as_ref() {
-            "-" => analyse_flac_file(stdin.lock().by_ref()),
-            x => analyse_flac_file(BufReader::new(File::open(x).unwrap()).by_ref()),
+        let metadata = match input.as_ref() {
+            "-" => parse(stdin.lock().by_ref()),
+            x => parse_path(x),
         }.unwrap();
+        print_metadata(metadata);
     } else {
         eprintln!("Supply the file to dump as the first argument");
         exit(1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,51 @@
+use std::io::prelude::*;
+
+use crate::{FlacError, Result};
+
+/// Represents an APPLICATION block, which holds data of a third party application
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Application {
+    /// The registered application ID, e.g. `riff` or `aiff`
+    pub id: [u8; 4],
+    /// The application data
+    pub data: Vec<u8>,
+}
+
+/// Parses an APPLICATION block of the given length if the reader is positioned at the start of the block
+pub fn parse_application<R>(file: &mut R, length: u32) -> Result<Application>
+where R: Read {
+    // The block starts with the 4 byte application ID, the rest is application data
+    let data_length = match length.checked_sub(4) {
+        Some(l) => l,
+        None => return Err(FlacError::MalformedBlock { block_type: 2, declared_size: length }),
+    };
+
+    let mut id = [0; 4];
+    file.read_exact(&mut id)?;
+
+    let mut data = vec![0; data_length as usize];
+    file.read_exact(&mut data)?;
+
+    Ok(Application { id, data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_is_split_from_the_data() {
+        let application = parse_application(&mut &b"riffdata"[..], 8).unwrap();
+        assert_eq!(&application.id, b"riff");
+        assert_eq!(application.data, b"data");
+    }
+
+    #[test]
+    fn block_shorter_than_an_id_is_malformed() {
+        match parse_application(&mut &b"rif"[..], 3) {
+            Err(FlacError::MalformedBlock { block_type: 2, declared_size: 3 }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use tokio::io::{self, AsyncBufRead, AsyncRead, AsyncReadExt};
+
+use crate::{FlacError, MusicMetaData, ParseOptions, Result};
+use crate::bytes::{decode_block_header, decode_vorbis_comments};
+use crate::parser::malformed_block;
+
+/// Parses the metadata of a FLAC file from an asynchronous reader, like `parse` does for a
+/// blocking one
+///
+/// Blocks other than the VORBIS_COMMENT block are skipped by reading them, the reader never has to
+/// seek.
+pub async fn parse_async<R>(file: &mut R) -> Result<MusicMetaData>
+where R: AsyncRead + AsyncBufRead + Unpin {
+    let options = ParseOptions::default();
+    let mut marker = [0; 4];
+    match file.read_exact(&mut marker).await {
+        Ok(_) => (),
+        // A file that is too short for the marker is not a FLAC file
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(FlacError::NotFlac),
+        Err(e) => return Err(e.into()),
+    }
+    match &marker {
+        b"fLaC" => (),
+        b"OggS" => return Err(FlacError::OggEncapsulated),
+        _ => return Err(FlacError::NotFlac),
+    }
+    loop {
+        let mut header = [0; 4];
+        file.read_exact(&mut header).await?;
+        let header = decode_block_header(&header);
+        if header.block_type == 4 {
+            if header.length as usize > options.max_comment_size {
+                return Err(FlacError::CommentTooLarge);
+            }
+            let mut body = vec![0; header.length as usize];
+            match file.read_exact(&mut body).await {
+                Ok(_) => (),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(malformed_block(&header)),
+                Err(e) => return Err(e.into()),
+            }
+            return decode_vorbis_comments(&body, &options).map(|x| x.into());
+        }
+        let skipped = io::copy_buf(&mut (&mut *file).take(u64::from(header.length)), &mut io::sink()).await?;
+        if skipped != u64::from(header.length) {
+            return Err(malformed_block(&header));
+        }
+        if header.is_last {
+            return Err(FlacError::NoCommentBlock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_slice, MusicData};
+    use crate::test_util::{block, comments, flac, stream_info};
+
+    #[tokio::test]
+    async fn same_result_as_parse() {
+        let file = flac(&[block(0, false, &stream_info()), block(1, false, &[0; 20_000]), block(4, true, &comments(&[("TITLE", "Get Got"), ("ARTIST", "Death Grips")]))]);
+        let metadata = parse_async(&mut io::BufReader::new(&file[..])).await.unwrap();
+        assert_eq!(metadata.title(), Some("Get Got"));
+        assert_eq!(metadata, parse_slice(&file).unwrap());
+    }
+
+    #[tokio::test]
+    async fn truncated_block() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(1, true, &[0; 100])]);
+        file.truncate(file.len() - 1);
+        match parse_async(&mut io::BufReader::new(&file[..])).await {
+            Err(FlacError::MalformedBlock { block_type: 1, declared_size: 100 }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn without_comments() {
+        let file = flac(&[block(0, true, &stream_info())]);
+        assert!(matches!(parse_async(&mut io::BufReader::new(&file[..])).await, Err(FlacError::NoCommentBlock)));
+        assert!(matches!(parse_async(&mut io::BufReader::new(&b"OggS"[..])).await, Err(FlacError::OggEncapsulated)));
+        assert!(matches!(parse_async(&mut io::BufReader::new(&b"fL"[..])).await, Err(FlacError::NotFlac)));
+    }
+}
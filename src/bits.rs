@@ -0,0 +1,96 @@
+use std::cmp;
+use std::io::prelude::*;
+
+use byteorder::ReadBytesExt;
+
+use crate::Result;
+
+/// Reads numbers of arbitrary bit widths, for fields that are packed across byte boundaries
+///
+/// Bytes are only read from the reader when their bits are needed, so after reading a multiple of
+/// 8 bits the reader is positioned right after those bits.
+pub struct BitReader<R>
+where R: Read {
+    reader: R,
+    /// The current byte, of which the lowest `available` bits have not been read yet
+    byte: u8,
+    available: u32,
+}
+
+impl<R> BitReader<R>
+where R: Read {
+    pub fn new(reader: R) -> BitReader<R> {
+        BitReader { reader, byte: 0, available: 0 }
+    }
+
+    /// Read a big endian number of at most 64 bits
+    pub fn read_bits(&mut self, mut n: u32) -> Result<u64> {
+        debug_assert!(n <= 64);
+        let mut value: u64 = 0;
+        while n > 0 {
+            if self.available == 0 {
+                self.byte = self.reader.read_u8()?;
+                self.available = 8;
+            }
+            let take = cmp::min(n, self.available);
+            let bits = (u32::from(self.byte) >> (self.available - take)) & ((1 << take) - 1);
+            value = value << take | u64::from(bits);
+            self.available -= take;
+            n -= take;
+        }
+        Ok(value)
+    }
+
+    /// Read a single bit as a flag
+    pub fn read_bit(&mut self) -> Result<bool> {
+        self.read_bits(1).map(|bit| bit == 1)
+    }
+
+    /// Skip a number of bits, like reserved fields
+    pub fn skip_bits(&mut self, mut n: u32) -> Result<()> {
+        while n > 0 {
+            let take = cmp::min(n, 64);
+            self.read_bits(take)?;
+            n -= take;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_span_byte_boundaries() {
+        // The 64 bits of STREAMINFO with 44.1 kHz, 2 channels, 16 bits and 0x1_2345_6789 samples
+        let data = [0x0A, 0xC4, 0x42, 0xF1, 0x23, 0x45, 0x67, 0x89];
+        let mut bits = BitReader::new(&data[..]);
+        assert_eq!(bits.read_bits(20).unwrap(), 44_100);
+        assert_eq!(bits.read_bits(3).unwrap(), 1);
+        assert_eq!(bits.read_bits(5).unwrap(), 15);
+        assert_eq!(bits.read_bits(36).unwrap(), 0x1_2345_6789);
+    }
+
+    #[test]
+    fn single_bits_and_skipped_bits() {
+        let mut bits = BitReader::new(&[0b1010_0000, 0xFF][..]);
+        assert!(bits.read_bit().unwrap());
+        assert!(!bits.read_bit().unwrap());
+        bits.skip_bits(9).unwrap();
+        assert_eq!(bits.read_bits(5).unwrap(), 0b11111);
+    }
+
+    #[test]
+    fn full_64_bit_value() {
+        let mut bits = BitReader::new(&[0x80, 0, 0, 0, 0, 0, 0, 0x01][..]);
+        assert_eq!(bits.read_bits(64).unwrap(), 0x8000_0000_0000_0001);
+    }
+
+    #[test]
+    fn reading_past_the_end_is_an_error() {
+        let mut bits = BitReader::new(&[0xFF][..]);
+        assert_eq!(bits.read_bits(4).unwrap(), 0xF);
+        assert!(bits.read_bits(5).is_err());
+    }
+}
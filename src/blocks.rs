@@ -0,0 +1,166 @@
+use std::io::prelude::*;
+
+use crate::{MusicMetaData, ParseOptions, Result, VorbisMetadata};
+use crate::bytes::BlockHeader;
+use crate::application::{parse_application, Application};
+use crate::cuesheet::{parse_cue_sheet, CueSheet};
+use crate::parser::{parse_body, parse_vorbis_comments, ReadState};
+use crate::picture::{parse_picture, Picture};
+use crate::seektable::{parse_seek_table, SeekPoint};
+use crate::streaminfo::{parse_stream_info, StreamInfo};
+
+/// Represents a single metadata block of a FLAC file
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Block {
+    /// The STREAMINFO block
+    StreamInfo(StreamInfo),
+    /// A PADDING block of the given number of bytes
+    Padding(u32),
+    /// An APPLICATION block
+    Application(Application),
+    /// The seek points of a SEEKTABLE block
+    SeekTable(Vec<SeekPoint>),
+    /// A VORBIS_COMMENT block
+    VorbisComment(VorbisMetadata),
+    /// A CUESHEET block
+    CueSheet(CueSheet),
+    /// A PICTURE block
+    Picture(Picture),
+    /// A block of a type that is reserved or invalid
+    Unknown {
+        /// The block type from the header
+        block_type: u8,
+        /// The raw contents of the block
+        data: Vec<u8>,
+    },
+}
+
+/// All metadata of a FLAC file, as parsed by `FlacParser::parse_all`
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FlacMetadata {
+    /// The STREAMINFO block, only `None` if it was already read or the file is malformed
+    pub stream_info: Option<StreamInfo>,
+    /// The VORBIS_COMMENT block, if the file has one
+    pub comments: Option<VorbisMetadata>,
+    /// All PICTURE blocks
+    pub pictures: Vec<Picture>,
+    /// The seek points of the SEEKTABLE block
+    pub seek_table: Vec<SeekPoint>,
+    /// All APPLICATION blocks
+    pub applications: Vec<Application>,
+    /// The CUESHEET block, if the file has one
+    pub cue_sheet: Option<CueSheet>,
+}
+
+/// The position of a metadata block within a FLAC file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BlockEntry {
+    /// The block type from the header
+    pub block_type: u8,
+    /// The offset of the block header from the start of the file
+    pub offset: u64,
+    /// The length of the block body, excluding the 4 byte header
+    pub length: u32,
+    /// True if this is the last metadata block
+    pub is_last: bool,
+}
+
+/// An overview of the metadata blocks of a FLAC file, as returned by `FlacParser::summary`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FlacSummary {
+    /// The number of metadata blocks
+    pub block_count: usize,
+    /// True if there is at least one PICTURE block
+    pub has_pictures: bool,
+    /// True if there is a VORBIS_COMMENT block
+    pub has_comments: bool,
+    /// True if there is a CUESHEET block
+    pub has_cuesheet: bool,
+    /// The offset of the first audio frame from the start of the reader
+    pub audio_offset: u64,
+}
+
+/// The metadata of a file as parsed by `parse_path_checked`, together with whether its contents
+/// match its extension
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParsedFile {
+    /// The metadata, or `None` if the contents are neither FLAC nor Ogg or there is no comment
+    /// block
+    pub metadata: Option<MusicMetaData>,
+    extension_matches: bool,
+}
+
+impl ParsedFile {
+    pub(crate) fn new(metadata: Option<MusicMetaData>, extension_matches: bool) -> ParsedFile {
+        ParsedFile { metadata, extension_matches }
+    }
+
+    /// Returns false for mislabeled files, like an MP3 file with a `.flac` extension
+    ///
+    /// The extension is compared case-insensitively, `.flac` and `.fla` are expected to be FLAC
+    /// files and `.ogg`, `.oga` and `.opus` are expected to be Ogg files. Any other extension, or
+    /// no extension, matches all contents except FLAC and Ogg.
+    pub fn extension_matches_content(&self) -> bool {
+        self.extension_matches
+    }
+}
+
+/// An iterator over the metadata blocks of a FLAC file
+///
+/// The iterator stops after the block that has the last-block flag set, so the reader is never
+/// advanced into the audio frames
+pub struct MetadataBlocks<'a, R>
+where R: 'a + Read {
+    file: &'a mut R,
+    state: &'a mut ReadState,
+    options: ParseOptions,
+}
+
+impl<'a, R> MetadataBlocks<'a, R>
+where R: Read {
+    pub(crate) fn new(file: &'a mut R, state: &'a mut ReadState, options: ParseOptions) -> MetadataBlocks<'a, R> {
+        MetadataBlocks { file, state, options }
+    }
+}
+
+impl<'a, R> Iterator for MetadataBlocks<'a, R>
+where R: Read {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Result<Block>> {
+        let result = match self.state.read_header(self.file.by_ref(), &self.options) {
+            Ok(Some(header)) => parse_block(self.file.by_ref(), &header, &self.options),
+            Ok(None) => return None,
+            Err(e) => Err(e),
+        };
+        // A failed block leaves the reader at an unknown position, so don't try to continue
+        if result.is_err() {
+            self.state.done = true;
+        }
+        Some(result)
+    }
+}
+
+/// Parses a metadata block if the reader is positioned at the start of its body
+fn parse_block<R>(file: &mut R, header: &BlockHeader, options: &ParseOptions) -> Result<Block>
+where R: Read {
+    parse_body(file, header, |body| Ok(match header.block_type {
+        0 => Block::StreamInfo(parse_stream_info(body)?),
+        1 => Block::Padding(header.length),
+        2 => Block::Application(parse_application(body, header.length)?),
+        3 => Block::SeekTable(parse_seek_table(body, header.length)?),
+        4 => Block::VorbisComment(parse_vorbis_comments(body, options)?),
+        5 => Block::CueSheet(parse_cue_sheet(body, options)?),
+        6 => Block::Picture(parse_picture(body, header.length, options)?),
+        block_type => {
+            let mut data = Vec::new();
+            body.read_to_end(&mut data)?;
+            Block::Unknown { block_type, data }
+        },
+    }))
+}
@@ -0,0 +1,102 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str;
+
+use crate::{normalize_key, FlacError, Map, MusicData, ParseOptions, Result};
+use crate::bytes::split_vorbis_comments;
+
+/// Represents a Vorbis comment block that borrows its strings from the block data
+///
+/// Unlike `VorbisMetadata` no strings are allocated. Field names keep the case and whitespace of
+/// the file, but they are still compared case-insensitively and ignoring surrounding whitespace.
+#[derive(Debug)]
+pub struct BorrowedVorbisMetadata<'a> {
+    vendor_string: &'a str,
+    user_comments: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> BorrowedVorbisMetadata<'a> {
+    /// Get the vendor string, which identifies the encoder that wrote the file
+    pub fn vendor(&self) -> &'a str {
+        self.vendor_string
+    }
+
+    /// Get all values of a field, in the order they appear in the file
+    pub fn get_all<'s>(&'s self, key: &'s str) -> impl Iterator<Item = &'a str> + 's {
+        self.user_comments.iter()
+            .filter(move |(k, _)| key_matches(k, key))
+            .map(|&(_, value)| value)
+    }
+
+    /// Get the number of user comments
+    pub fn len(&self) -> usize {
+        self.user_comments.len()
+    }
+
+    /// Returns true if there are no user comments
+    pub fn is_empty(&self) -> bool {
+        self.user_comments.is_empty()
+    }
+
+    /// Returns true if the field has at least one value, field names are case-insensitive
+    pub fn contains(&self, key: &str) -> bool {
+        self.get_all(key).next().is_some()
+    }
+
+    /// Iterate over all fields and their values, in the order they appear in the file
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        self.user_comments.iter().cloned()
+    }
+}
+
+impl<'a, 'b> MusicData<'a> for BorrowedVorbisMetadata<'b> {
+    fn title(&'a self) -> Option<&'a str> {
+        self.get("TITLE")
+    }
+    fn artist(&'a self) -> Option<&'a str> {
+        self.get("ARTIST")
+    }
+    fn album(&'a self) -> Option<&'a str> {
+        self.get("ALBUM")
+    }
+    fn get(&'a self, key: &str) -> Option<&'a str> {
+        self.user_comments.iter().find(|(k, _)| key_matches(k, key)).map(|&(_, value)| value)
+    }
+    fn iter(&'a self) -> Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a> {
+        Box::new(BorrowedVorbisMetadata::iter(self))
+    }
+    /// Get a map with the first value of every field, with the field names in uppercase
+    fn map(self) -> Map<String, String> {
+        let mut map = Map::new();
+        for (key, value) in self.user_comments {
+            map.entry(normalize_key(key)).or_insert_with(|| value.into());
+        }
+        map
+    }
+}
+
+/// Compares field names like `normalize_key` does, without allocating
+fn key_matches(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+/// Parses the body of a VORBIS_COMMENT block without copying the strings
+///
+/// The data has to be the body of the block, without the block header. Invalid UTF-8 results in
+/// `FlacError::InvalidUtf8`, because the strings can't be replaced without allocating.
+pub fn parse_borrowed(data: &[u8]) -> Result<BorrowedVorbisMetadata<'_>> {
+    let raw = split_vorbis_comments(data, &ParseOptions::default())?;
+    let decode = |bytes| str::from_utf8(bytes).map_err(|_| FlacError::InvalidUtf8 { key: None });
+    Ok(BorrowedVorbisMetadata {
+        vendor_string: decode(raw.vendor)?,
+        user_comments: raw.comments.into_iter()
+            .map(|(key, value)| {
+                let key = decode(key)?;
+                let value = str::from_utf8(value)
+                    .map_err(|_| FlacError::InvalidUtf8 { key: Some(normalize_key(key)) })?;
+                Ok((key, value))
+            })
+            .collect::<Result<_>>()?,
+    })
+}
@@ -0,0 +1,343 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp;
+use core::convert::TryFrom;
+use core::str;
+
+use byteorder::{ByteOrder, BigEndian, LittleEndian};
+
+use crate::{normalize_key, FlacError, Map, ParseOptions, Result, VorbisMetadata};
+
+/// A reader over a byte slice, which allows parsing without `std::io`
+pub struct SliceReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(data: &'a [u8]) -> SliceReader<'a> {
+        SliceReader { data, position: 0 }
+    }
+
+    /// Read the next n bytes, results in `FlacError::Truncated` if fewer bytes are left
+    pub fn read_bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.data.len() - self.position {
+            return Err(FlacError::Truncated);
+        }
+        let bytes = &self.data[self.position..self.position + n];
+        self.position += n;
+        Ok(bytes)
+    }
+
+    /// Get the number of bytes that have not been read yet
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    /// Read a little endian 32 bit integer
+    pub fn read_u32_le(&mut self) -> Result<u32> {
+        self.read_bytes(4).map(LittleEndian::read_u32)
+    }
+}
+
+/// The header that precedes every metadata block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// True if this is the last metadata block before the audio frames
+    pub is_last: bool,
+    /// The type of the block, like 4 for VORBIS_COMMENT
+    pub block_type: u8,
+    /// The length of the block body, excluding the 4 byte header
+    pub length: u32,
+}
+
+/// Decodes the 4 bytes of a metadata block header
+pub fn decode_block_header(bytes: &[u8; 4]) -> BlockHeader {
+    BlockHeader {
+        is_last: bytes[0] >> 7 == 1,
+        block_type: bytes[0] & 0b0111_1111,
+        length: BigEndian::read_u24(&bytes[1..]),
+    }
+}
+
+/// Decodes the body of a VORBIS_COMMENT block
+pub fn decode_vorbis_comments(data: &[u8], options: &ParseOptions) -> Result<VorbisMetadata> {
+    collect_comments(split_vorbis_comments(data, options)?, options)
+}
+
+/// Encodes the body of a VORBIS_COMMENT block, the counterpart of `decode_vorbis_comments`
+///
+/// Every comment is written as `KEY=VALUE` with the key as given, the lengths are little endian.
+pub fn encode_vorbis_comment(vendor: &str, comments: &[(String, String)]) -> Vec<u8> {
+    let size = comments.iter().fold(8 + vendor.len(), |size, (key, value)| size + 5 + key.len() + value.len());
+    let mut block = Vec::with_capacity(size);
+    write_u32_le(&mut block, vendor.len() as u32);
+    block.extend_from_slice(vendor.as_bytes());
+    write_u32_le(&mut block, comments.len() as u32);
+    for (key, value) in comments {
+        write_u32_le(&mut block, (key.len() + 1 + value.len()) as u32);
+        block.extend_from_slice(key.as_bytes());
+        block.push(b'=');
+        block.extend_from_slice(value.as_bytes());
+    }
+    block
+}
+
+/// Appends a little endian 32 bit integer to the buffer
+fn write_u32_le(buf: &mut Vec<u8>, n: u32) {
+    let mut bytes = [0; 4];
+    LittleEndian::write_u32(&mut bytes, n);
+    buf.extend_from_slice(&bytes);
+}
+
+/// Decodes a Vorbis comment header as used in Ogg Vorbis, which ends with a framing bit that FLAC
+/// omits, returns whether the framing bit is set
+///
+/// The data starts after the `\x03vorbis` packet type. With the `strict` option, a missing
+/// framing bit results in `FlacError::InvalidStructure`.
+pub fn parse_vorbis_comments_with_framing(data: &[u8], options: &ParseOptions) -> Result<(VorbisMetadata, bool)> {
+    let raw = split_vorbis_comments(data, options)?;
+    let framing = raw.rest.first().is_some_and(|b| b & 1 == 1);
+    if options.strict && !framing {
+        return Err(FlacError::InvalidStructure("the Vorbis comment header must end with a framing bit"));
+    }
+    Ok((collect_comments(raw, options)?, framing))
+}
+
+/// Decodes the strings of a comment block and collects them in a map
+fn collect_comments(raw: RawComments, options: &ParseOptions) -> Result<VorbisMetadata> {
+    // Vorbis comments support vendor strings
+    let vendor_string = decode_string(raw.vendor.to_vec(), options)?;
+
+    let mut comments = Map::new();
+    let mut order = Vec::with_capacity(raw.comments.len());
+
+    // Read all the lines into a map
+    for (key_bytes, value_bytes) in raw.comments {
+        let (key, value) = decode_comment(key_bytes, value_bytes, options)?;
+        // The original bytes are only kept if decoding changed them, e.g. by replacing invalid
+        // UTF-8 or stripping a byte order mark
+        let raw = if value.as_bytes() != value_bytes { Some(value_bytes.to_vec()) } else { None };
+        let values = comments.entry(key.clone()).or_insert_with(Vec::new);
+        order.push((key, values.len(), raw));
+        values.push(value);
+    }
+
+    Ok(VorbisMetadata{vendor_string, user_comments: comments, order, warnings: raw.warnings})
+}
+
+/// Decodes the field name and value of a user comment, the field name is normalized to uppercase
+pub fn decode_comment(key: &[u8], value: &[u8], options: &ParseOptions) -> Result<(String, String)> {
+    let key = normalize_key(&decode_string(key.to_vec(), options)?);
+    let mut value = match str::from_utf8(value) {
+        Ok(value) => value.into(),
+        Err(_) => decode_string(value.to_vec(), options)
+            .map_err(|_| FlacError::InvalidUtf8 { key: Some(key.clone()) })?,
+    };
+    if options.strip_bom && value.starts_with('\u{FEFF}') {
+        value.drain(..'\u{FEFF}'.len_utf8());
+    }
+    Ok((key, value))
+}
+
+/// The undecoded contents of a VORBIS_COMMENT block
+pub struct RawComments<'a> {
+    /// The vendor string
+    pub vendor: &'a [u8],
+    /// The field names and values of the user comments
+    pub comments: Vec<(&'a [u8], &'a [u8])>,
+    /// The problems that were skipped with the `tolerant` option
+    pub warnings: Vec<String>,
+    /// The data after the last comment, like the framing bit of a Vorbis comment header
+    pub rest: &'a [u8],
+}
+
+/// Splits the body of a VORBIS_COMMENT block into the vendor string and the user comments,
+/// without decoding them
+pub fn split_vorbis_comments<'a>(data: &'a [u8], options: &ParseOptions) -> Result<RawComments<'a>> {
+    let mut reader = SliceReader::new(data);
+
+    let mut warnings = Vec::new();
+
+    let vendor = {
+        let (length, big_endian) = read_comment_length(&mut reader, options)?;
+        if big_endian {
+            warnings.push("read the length of the vendor string as big-endian".into());
+        }
+        reader.read_bytes(length)?
+    };
+
+    let ncomments = reader.read_u32_le()?;
+    // Every comment takes at least 4 bytes, so a bogus count can't cause a huge allocation
+    let capacity = usize::try_from(ncomments).unwrap_or(usize::MAX);
+    let mut comments = Vec::with_capacity(cmp::min(capacity, data.len() / 4));
+    let mut total: usize = 0;
+    for i in 0..ncomments {
+        let (length, big_endian) = read_comment_length(&mut reader, options)?;
+        total = total.saturating_add(length);
+        if total > options.max_total_comments_bytes {
+            return Err(FlacError::CommentTotalTooLarge);
+        }
+        if big_endian {
+            warnings.push(format!("read the length of comment {} as big-endian", i));
+        }
+        let comment = reader.read_bytes(length)?;
+
+        // Only the first '=' separates the field name, values may contain '=' themselves
+        match comment.iter().position(|&b| b == b'=') {
+            // Field names may only contain the ASCII characters 0x20 to 0x7D, except for '='
+            Some(i) if options.strict && comment[..i].iter().any(|b| !(0x20..=0x7D).contains(b)) => {
+                return Err(FlacError::InvalidKey);
+            },
+            Some(i) => comments.push((&comment[..i], &comment[i + 1..])),
+            // Without a '=' the flac file is malformed
+            None if options.tolerant => warnings.push(format!("skipped comment {}, it has no '='", i)),
+            None => return Err(FlacError::MalformedComment),
+        }
+    }
+
+    let rest = reader.read_bytes(reader.remaining())?;
+    Ok(RawComments { vendor, comments, warnings, rest })
+}
+
+/// Reads the length of a vendor string or comment and checks it against `max_comment_size`
+///
+/// Some buggy encoders wrote the lengths as big-endian. With the `tolerant` option, a length that
+/// is longer than the rest of the data is read as big-endian instead if that length fits, which is
+/// indicated by returning true.
+fn read_comment_length(reader: &mut SliceReader, options: &ParseOptions) -> Result<(usize, bool)> {
+    let mut length = reader.read_u32_le()?;
+    let mut big_endian = false;
+    if options.tolerant && u64::from(length) > reader.remaining() as u64
+        && u64::from(length.swap_bytes()) <= reader.remaining() as u64 {
+        length = length.swap_bytes();
+        big_endian = true;
+    }
+    // On 16 bit targets a length may not fit in usize, such a comment is too large in any case
+    let length = usize::try_from(length).map_err(|_| FlacError::CommentTooLarge)?;
+    if length > options.max_comment_size {
+        return Err(FlacError::CommentTooLarge);
+    }
+    Ok((length, big_endian))
+}
+
+/// Decodes bytes as a UTF-8 string, invalid UTF-8 is only accepted with the `lossy_utf8` option
+pub fn decode_string(bytes: Vec<u8>, options: &ParseOptions) -> Result<String> {
+    match String::from_utf8(bytes) {
+        Ok(string) => Ok(string),
+        Err(e) if options.lossy_utf8 => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+        Err(_) => Err(FlacError::InvalidUtf8 { key: None }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MusicData;
+    use crate::test_util::raw_comments;
+
+    #[test]
+    fn block_type_uses_all_seven_bits() {
+        let header = decode_block_header(&[0x44, 0x00, 0x01, 0x02]);
+        assert_eq!(header, BlockHeader { is_last: false, block_type: 68, length: 0x0102 });
+        let header = decode_block_header(&[0xFF, 0x00, 0x00, 0x00]);
+        assert_eq!(header, BlockHeader { is_last: true, block_type: 127, length: 0 });
+    }
+
+    #[test]
+    fn only_the_first_equals_sign_separates_the_field_name() {
+        let data = encode_vorbis_comment("test", &[("COMMENT".into(), "a=b=c".into()), ("URL".into(), "http://x/?a=1".into())]);
+        let metadata = decode_vorbis_comments(&data, &ParseOptions::new()).unwrap();
+        assert_eq!(metadata.get_all("COMMENT"), ["a=b=c"]);
+        assert_eq!(metadata.get_all("URL"), ["http://x/?a=1"]);
+    }
+
+    #[test]
+    fn empty_values_are_kept() {
+        let data = raw_comments(4, 3, &[b"KEY=", b"EQUALS==", b"TITLE=x"]);
+        for options in &[ParseOptions::new(), ParseOptions::new().lossy_utf8(true), ParseOptions::new().tolerant(true)] {
+            let metadata = decode_vorbis_comments(&data, options).unwrap();
+            assert_eq!(metadata.get("KEY"), Some(""));
+            assert_eq!(metadata.get("EQUALS"), Some("="));
+            assert_eq!(metadata.len(), 3);
+        }
+        let metadata = crate::parse_borrowed(&data).unwrap();
+        assert_eq!(metadata.get("KEY"), Some(""));
+        assert_eq!(metadata.map().get("KEY").map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn tolerant_skips_comments_without_equals_sign() {
+        let mut data = encode_vorbis_comment("test", &[("TITLE".into(), "x".into()), ("ARTIST".into(), "y".into())]);
+        // Replace the '=' of the first comment, after the vendor string, count and comment length
+        data[4 + 4 + 4 + 4 + 5] = b' ';
+        match decode_vorbis_comments(&data, &ParseOptions::new()) {
+            Err(FlacError::MalformedComment) => (),
+            other => panic!("expected MalformedComment, got {:?}", other),
+        }
+        let metadata = decode_vorbis_comments(&data, &ParseOptions::new().tolerant(true)).unwrap();
+        assert_eq!(metadata.get_all("ARTIST"), ["y"]);
+        assert_eq!(metadata.len(), 1);
+        assert_eq!(metadata.warnings(), ["skipped comment 0, it has no '='"]);
+    }
+
+    #[test]
+    fn strip_bom_keeps_the_original_bytes() {
+        let data = encode_vorbis_comment("test", &[("TITLE".into(), "\u{FEFF}T".into())]);
+        let metadata = decode_vorbis_comments(&data, &ParseOptions::new()).unwrap();
+        assert_eq!(metadata.get_all("TITLE"), ["\u{FEFF}T"]);
+        let metadata = decode_vorbis_comments(&data, &ParseOptions::new().strip_bom(true)).unwrap();
+        assert_eq!(metadata.get_all("TITLE"), ["T"]);
+        let raw: Vec<(&str, &[u8])> = metadata.raw_comments().collect();
+        assert_eq!(raw, [("TITLE", "\u{FEFF}T".as_bytes())]);
+    }
+
+    #[test]
+    fn vendor_length_past_the_end() {
+        assert!(matches!(decode_vorbis_comments(&raw_comments(u32::MAX, 0, &[]), &ParseOptions::new()), Err(FlacError::CommentTooLarge)));
+        assert!(matches!(decode_vorbis_comments(&raw_comments(100, 0, &[]), &ParseOptions::new()), Err(FlacError::Truncated)));
+    }
+
+    #[test]
+    fn comment_count_past_the_end() {
+        let data = raw_comments(4, u32::MAX, &[b"A=b"]);
+        assert!(matches!(decode_vorbis_comments(&data, &ParseOptions::new()), Err(FlacError::Truncated)));
+    }
+
+    #[test]
+    fn comment_length_u32_max() {
+        let mut data = raw_comments(4, 1, &[]);
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(decode_vorbis_comments(&data, &ParseOptions::new()), Err(FlacError::CommentTooLarge)));
+    }
+
+    #[test]
+    fn comment_without_equals_sign() {
+        let data = raw_comments(4, 1, &[b"TITLE"]);
+        assert!(matches!(decode_vorbis_comments(&data, &ParseOptions::new()), Err(FlacError::MalformedComment)));
+    }
+
+    #[test]
+    fn invalid_utf8_value() {
+        let data = raw_comments(4, 1, &[b"TITLE=\xC3"]);
+        match decode_vorbis_comments(&data, &ParseOptions::new()) {
+            Err(FlacError::InvalidUtf8 { key: Some(ref key) }) if key == "TITLE" => (),
+            other => panic!("expected InvalidUtf8, got {:?}", other),
+        }
+        let metadata = decode_vorbis_comments(&data, &ParseOptions::new().lossy_utf8(true)).unwrap();
+        assert_eq!(metadata.get_all("TITLE"), ["\u{FFFD}"]);
+    }
+
+    #[test]
+    fn framed_and_unframed_comment_headers() {
+        let mut data = encode_vorbis_comment("test", &[("TITLE".into(), "x".into())]);
+        let (metadata, framing) = parse_vorbis_comments_with_framing(&data, &ParseOptions::new()).unwrap();
+        assert!(!framing);
+        assert_eq!(metadata.get_all("TITLE"), ["x"]);
+        assert!(matches!(parse_vorbis_comments_with_framing(&data, &ParseOptions::new().strict(true)), Err(FlacError::InvalidStructure(_))));
+        data.push(1);
+        let (metadata, framing) = parse_vorbis_comments_with_framing(&data, &ParseOptions::new().strict(true)).unwrap();
+        assert!(framing);
+        assert_eq!(metadata.get_all("TITLE"), ["x"]);
+    }
+}
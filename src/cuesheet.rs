@@ -0,0 +1,149 @@
+use std::io::prelude::*;
+use std::time::Duration;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use crate::{ParseOptions, Result};
+use crate::bits::BitReader;
+use crate::bytes::decode_string;
+use crate::parser::skip_n;
+use crate::streaminfo::StreamInfo;
+
+/// Represents a CUESHEET block, which describes the track layout of e.g. a CD image
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CueSheet {
+    /// The media catalog number, may be empty
+    pub media_catalog_number: String,
+    /// The number of lead-in samples, only meaningful for CD-DA cue sheets
+    pub lead_in: u64,
+    /// True if the cue sheet corresponds to a Compact Disc
+    pub is_cd: bool,
+    /// The tracks of the cue sheet, the last one is the lead-out track
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Get the number and start time of every track, for presenting a whole-disc file as separate
+    /// tracks
+    ///
+    /// A track starts at its index point 1 if it has one, so a pregap belongs to the track before
+    /// it. The lead-out track is left out, it only marks the end of the last track. The result is
+    /// empty if the sample rate is unknown.
+    pub fn tracks_with_times(&self, stream_info: &StreamInfo) -> Vec<(u32, Duration)> {
+        let tracks = match self.tracks.split_last() {
+            Some((_, tracks)) => tracks,
+            None => return Vec::new(),
+        };
+        tracks.iter().filter_map(|track| {
+            let index = track.indices.iter().find(|index| index.number == 1).map_or(0, |index| index.offset);
+            let start = track.offset.checked_add(index)?;
+            stream_info.sample_to_time(start).map(|time| (u32::from(track.number), time))
+        }).collect()
+    }
+}
+
+/// Represents a track of a CUESHEET block
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CueTrack {
+    /// The offset in samples of the first index point relative to the beginning of the audio
+    pub offset: u64,
+    /// The track number, 170 (CD-DA) or 255 (other) for the lead-out track
+    pub number: u8,
+    /// The International Standard Recording Code, may be empty
+    pub isrc: String,
+    /// True for audio tracks, false for non-audio tracks
+    pub is_audio: bool,
+    /// True if the track has pre-emphasis
+    pub pre_emphasis: bool,
+    /// The index points of the track
+    pub indices: Vec<CueIndex>,
+}
+
+/// Represents an index point of a track in a CUESHEET block
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CueIndex {
+    /// The offset in samples relative to the offset of the track
+    pub offset: u64,
+    /// The index point number
+    pub number: u8,
+}
+
+/// Parses a CUESHEET block if the reader is positioned at the start of the block
+pub fn parse_cue_sheet<R>(file: &mut R, options: &ParseOptions) -> Result<CueSheet>
+where R: Read {
+    let media_catalog_number = read_padded_string(file.by_ref(), 128, options)?;
+    let lead_in = file.read_u64::<BigEndian>()?;
+    // Only the first bit is used, the other 7 bits and the following 258 bytes are reserved
+    let is_cd = {
+        let mut bits = BitReader::new(file.by_ref());
+        let is_cd = bits.read_bit()?;
+        bits.skip_bits(7 + 258 * 8)?;
+        is_cd
+    };
+
+    let ntracks = file.read_u8()?;
+    let mut tracks = Vec::with_capacity(ntracks as usize);
+    for _ in 0..ntracks {
+        tracks.push(parse_cue_track(file.by_ref(), options)?);
+    }
+
+    Ok(CueSheet { media_catalog_number, lead_in, is_cd, tracks })
+}
+
+/// Parses a single track of a CUESHEET block
+fn parse_cue_track<R>(file: &mut R, options: &ParseOptions) -> Result<CueTrack>
+where R: Read {
+    let offset = file.read_u64::<BigEndian>()?;
+    let number = file.read_u8()?;
+    let isrc = read_padded_string(file.by_ref(), 12, options)?;
+    // The first two bits are used, the other 6 bits and the following 13 bytes are reserved
+    let (is_audio, pre_emphasis) = {
+        let mut bits = BitReader::new(file.by_ref());
+        let is_audio = !bits.read_bit()?;
+        let pre_emphasis = bits.read_bit()?;
+        bits.skip_bits(6 + 13 * 8)?;
+        (is_audio, pre_emphasis)
+    };
+
+    let nindices = file.read_u8()?;
+    let mut indices = Vec::with_capacity(nindices as usize);
+    for _ in 0..nindices {
+        let offset = file.read_u64::<BigEndian>()?;
+        let number = file.read_u8()?;
+        // Every index point ends with 3 reserved bytes
+        skip_n(file.by_ref(), 3)?;
+        indices.push(CueIndex { offset, number });
+    }
+
+    Ok(CueTrack {
+        offset,
+        number,
+        isrc,
+        is_audio,
+        pre_emphasis,
+        indices,
+    })
+}
+
+/// Read a fixed size string that is padded with NUL characters
+fn read_padded_string<R>(file: &mut R, size: usize, options: &ParseOptions) -> Result<String>
+where R: Read {
+    let mut buf = vec![0; size];
+    file.read_exact(&mut buf)?;
+    let end = buf.iter().position(|&b| b == 0).unwrap_or(size);
+    buf.truncate(end);
+    decode_string(buf, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_cue_sheet() {
+        assert!(parse_cue_sheet(&mut &[0; 200][..], &ParseOptions::new()).is_err());
+    }
+}
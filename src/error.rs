@@ -0,0 +1,129 @@
+use alloc::string::String;
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io;
+
+/// The result type used throughout this crate
+pub type Result<T> = result::Result<T, FlacError>;
+
+/// The errors that can occur while parsing metadata
+#[derive(Debug)]
+pub enum FlacError {
+    /// The reader does not contain a FLAC stream
+    NotFlac,
+    /// The reader contains an Ogg stream instead of a native FLAC stream, `OggParser` can parse
+    /// the comments of FLAC streams in Ogg
+    OggEncapsulated,
+    /// The reader does not contain an Ogg stream
+    NotOgg,
+    /// The FLAC stream has no STREAMINFO block
+    NoStreamInfo,
+    /// The FLAC stream has no VORBIS_COMMENT block
+    NoCommentBlock,
+    /// A user comment is not of the form `FIELD=value`
+    MalformedComment,
+    /// A comment block or a single comment is larger than the `max_comment_size` option allows
+    CommentTooLarge,
+    /// The user comments together are larger than the `max_total_comments_bytes` option allows
+    CommentTotalTooLarge,
+    /// A field name contains characters that the Vorbis comment specification does not allow
+    InvalidKey,
+    /// A PICTURE block has a picture type outside of the range defined by the specification
+    InvalidPictureType(u32),
+    /// A string in a metadata block is not valid UTF-8, e.g. because its length ends in the middle
+    /// of a multibyte character
+    InvalidUtf8 {
+        /// The field name if the string is the value of a user comment, `None` if the field name
+        /// itself or a string outside the user comments is invalid
+        key: Option<String>,
+    },
+    /// A length in a metadata block points past the end of the data
+    Truncated,
+    /// A metadata block header claims a length that is longer than the rest of the file, or a
+    /// field in the block claims a length that is longer than the rest of the block
+    MalformedBlock {
+        /// The block type from the header
+        block_type: u8,
+        /// The length of the block body according to the header
+        declared_size: u32,
+    },
+    /// A metadata block that is written is larger than the 24 bit length in its header allows
+    BlockTooLarge {
+        /// The type of the block
+        block_type: u8,
+        /// The length of the block body
+        size: u64,
+    },
+    /// The metadata blocks don't follow the structure required by the specification
+    InvalidStructure(&'static str),
+    /// The operation needs a feature that is not supported by this crate
+    Unsupported(&'static str),
+    /// Reading from the underlying reader failed
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+impl fmt::Display for FlacError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FlacError::NotFlac => write!(f, "could not parse as a flac file"),
+            FlacError::OggEncapsulated => write!(f, "the stream is encapsulated in ogg, which needs an ogg parser"),
+            FlacError::NotOgg => write!(f, "could not parse as an ogg file"),
+            FlacError::NoStreamInfo => write!(f, "no streaminfo block"),
+            FlacError::NoCommentBlock => write!(f, "no comment block"),
+            FlacError::MalformedComment => write!(f, "malformed FLAC file, could not split user comment"),
+            FlacError::CommentTooLarge => write!(f, "comment is larger than the maximum comment size"),
+            FlacError::CommentTotalTooLarge => write!(f, "comments are larger than the maximum total size of the comments"),
+            FlacError::InvalidKey => write!(f, "malformed FLAC file, field name contains invalid characters"),
+            FlacError::InvalidPictureType(t) => write!(f, "malformed FLAC file, invalid picture type {}", t),
+            FlacError::InvalidUtf8 { key: Some(ref key) } => write!(f, "malformed FLAC file, the value of {} is not valid UTF-8", key),
+            FlacError::InvalidUtf8 { key: None } => write!(f, "malformed FLAC file, string is not valid UTF-8"),
+            FlacError::Truncated => write!(f, "malformed FLAC file, data is shorter than its length claims"),
+            FlacError::MalformedBlock { block_type, declared_size } => {
+                write!(f, "malformed FLAC file, block of type {} does not fit in the {} bytes it claims", block_type, declared_size)
+            },
+            FlacError::BlockTooLarge { block_type, size } => {
+                write!(f, "block of type {} is {} bytes long, which does not fit in a metadata block", block_type, size)
+            },
+            FlacError::InvalidStructure(what) => write!(f, "malformed FLAC file, {}", what),
+            FlacError::Unsupported(what) => write!(f, "{} is not supported", what),
+            #[cfg(feature = "std")]
+            FlacError::Io(ref e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for FlacError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            FlacError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for FlacError {
+    fn from(e: io::Error) -> Self {
+        FlacError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<FlacError> for io::Error {
+    fn from(e: FlacError) -> Self {
+        match e {
+            FlacError::Io(e) => e,
+            FlacError::NoStreamInfo | FlacError::NoCommentBlock | FlacError::Truncated
+            | FlacError::MalformedBlock { .. } => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, e)
+            },
+            e @ FlacError::BlockTooLarge { .. } => io::Error::new(io::ErrorKind::InvalidInput, e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
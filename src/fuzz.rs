@@ -0,0 +1,76 @@
+//! Entry points for fuzzers, only compiled with `--cfg fuzzing` and for the tests
+//!
+//! The `fuzz` directory has a cargo-fuzz target that runs `fuzz` over generated inputs.
+
+use std::io::Cursor;
+
+use crate::{parse_any, parse_borrowed, parse_slice, FlacParser, MusicDataParser, OggParser, ParseOptions};
+use crate::bytes::parse_vorbis_comments_with_framing;
+
+/// Runs every parser over arbitrary bytes, malformed input must result in errors, never in a panic
+pub fn fuzz(data: &[u8]) {
+    let _ = parse_slice(data);
+    let _ = parse_any(&mut &data[..]);
+    let _ = parse_borrowed(data);
+
+    let options = [
+        ParseOptions::new(),
+        ParseOptions::new().strict(true),
+        ParseOptions::new().tolerant(true).lossy_utf8(true),
+    ];
+    for options in options.iter() {
+        if let Ok(mut parser) = FlacParser::with_options(&mut &data[..], *options) {
+            let _ = parser.parse_all();
+        }
+        if let Ok(mut parser) = FlacParser::with_options(&mut &data[..], *options) {
+            let _ = parser.blocks().count();
+        }
+        if let Ok(mut parser) = FlacParser::with_options(&mut &data[..], *options) {
+            let _ = parser.validate();
+        }
+        if let Ok(mut parser) = FlacParser::seekable_with_options(Cursor::new(data), *options) {
+            let _ = parser.block_layout();
+        }
+        if let Ok(mut parser) = OggParser::with_options(data, *options) {
+            let _ = parser.parse();
+        }
+        let _ = parse_vorbis_comments_with_framing(data, options);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{block, comments, flac, picture, raw_comments, stream_info};
+
+    #[test]
+    fn corrupted_inputs_do_not_panic() {
+        let mut id3 = b"ID3\x04\x00\x00\x7F\x7F\x7F\x7F".to_vec();
+        id3.extend(flac(&[block(0, true, &stream_info())]));
+        let mut huge_comment = raw_comments(4, 1, &[]);
+        huge_comment.extend_from_slice(&u32::MAX.to_le_bytes());
+        let inputs = [
+            Vec::new(),
+            b"fLa".to_vec(),
+            b"fLaC".to_vec(),
+            b"fLaC\x84\x00".to_vec(),
+            b"fLaC\x84\xFF\xFF\xFFtest".to_vec(),
+            id3,
+            flac(&[block(0, true, &stream_info()[..10])]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &raw_comments(u32::MAX, 0, &[]))]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &raw_comments(4, u32::MAX, &[b"A=b"]))]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &huge_comment)]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &raw_comments(4, 1, &[b"TITLE"]))]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &raw_comments(4, 1, &[b"TITLE=\xC3"]))]),
+            flac(&[block(0, false, &stream_info()), block(6, true, &picture(3, "image/png", b"png")[..12])]),
+            flac(&[block(0, false, &stream_info()), block(6, true, &picture(21, "image/png", b"png"))]),
+            flac(&[block(0, false, &stream_info()), block(2, true, b"ri")]),
+            flac(&[block(0, false, &stream_info()), block(5, true, &[0; 200])]),
+            flac(&[block(0, false, &stream_info()), block(4, false, &comments(&[("TITLE", "x")]))]),
+            b"OggS\x00\x02\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF".to_vec(),
+        ];
+        for input in inputs.iter() {
+            fuzz(input);
+        }
+    }
+}
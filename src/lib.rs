@@ -1,11 +1,98 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[macro_use]
+extern crate alloc;
 extern crate byteorder;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(feature = "std")]
+mod application;
+#[cfg(feature = "tokio")]
+mod async_parser;
+#[cfg(feature = "std")]
+mod bits;
+#[cfg(feature = "std")]
+mod blocks;
+mod borrowed;
+mod bytes;
+#[cfg(feature = "std")]
+mod cuesheet;
+mod error;
+#[cfg(all(any(fuzzing, test), feature = "std"))]
+pub mod fuzz;
+#[cfg(feature = "memmap2")]
+mod mmap;
+#[cfg(feature = "std")]
+mod ogg;
+mod options;
+#[cfg(feature = "std")]
+mod parser;
+#[cfg(feature = "std")]
+mod picture;
+mod replaygain;
+#[cfg(feature = "std")]
+mod seektable;
+#[cfg(feature = "std")]
+mod streaminfo;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "std")]
+mod writer;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::mem;
+use core::ops::Deref;
+use core::slice;
 
-use std::io::{Result, Error, ErrorKind};
-use std::io::prelude::*;
-use std::collections::HashMap;
-use std::ops::Deref;
+#[cfg(feature = "std")]
+use std::collections::HashMap as Map;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as Map;
 
-use byteorder::{ReadBytesExt, LittleEndian, BigEndian, ByteOrder};
+#[cfg(feature = "std")]
+pub use application::Application;
+#[cfg(feature = "tokio")]
+pub use async_parser::parse_async;
+#[cfg(feature = "std")]
+pub use blocks::{Block, BlockEntry, FlacMetadata, FlacSummary, MetadataBlocks, ParsedFile};
+pub use borrowed::{parse_borrowed, BorrowedVorbisMetadata};
+pub use bytes::{encode_vorbis_comment, parse_vorbis_comments_with_framing};
+use bytes::{decode_vorbis_comments, SliceReader};
+#[cfg(feature = "low-level")]
+pub use bytes::{decode_block_header, BlockHeader};
+#[cfg(not(feature = "low-level"))]
+use bytes::decode_block_header;
+#[cfg(feature = "std")]
+pub use cuesheet::{CueIndex, CueSheet, CueTrack};
+pub use error::{FlacError, Result};
+#[cfg(feature = "memmap2")]
+pub use mmap::parse_mmap;
+#[cfg(feature = "std")]
+pub use ogg::OggParser;
+pub use options::ParseOptions;
+#[cfg(feature = "std")]
+pub use parser::{extract_front_cover, parse, parse_any, parse_path, parse_path_checked, parse_slice, BulkParser, FlacParser};
+#[cfg(all(feature = "std", feature = "low-level"))]
+pub use parser::read_block_header;
+#[cfg(feature = "std")]
+pub use picture::{Picture, PictureType};
+pub use replaygain::ReplayGain;
+use replaygain::{parse_gain, parse_peak, parse_r128_gain};
+#[cfg(feature = "std")]
+pub use seektable::SeekPoint;
+#[cfg(feature = "std")]
+pub use streaminfo::{ChannelLayout, Md5Status, StreamInfo};
+#[cfg(feature = "std")]
+pub use writer::FlacWriter;
 
 /// This trait allows for different metadata specifications to be accessed by the same functions
 pub trait MusicData<'a> {
@@ -18,33 +105,448 @@ pub trait MusicData<'a> {
     /// Get the track number
     /// Note: this is a string because many metadata specifications allow for tracknumbers like
     /// A3 (side a, track 3)
-    fn tracknumber(&'a self) -> Option<&'a str>;
+    ///
+    /// If the track number is of the form `3/12`, only the `3` is returned
+    fn tracknumber(&'a self) -> Option<&'a str> {
+        self.get("TRACKNUMBER").map(numerator)
+    }
+    /// Get the side of a track number like `A3`, `None` for plain numeric track numbers
+    fn track_side(&'a self) -> Option<char> {
+        self.tracknumber().and_then(split_side).and_then(|(side, _)| side)
+    }
+    /// Get the numeric part of the track number, so 3 for both `3` and `A3`
+    fn track_index(&'a self) -> Option<u32> {
+        self.tracknumber().and_then(split_side).and_then(|(_, index)| index.parse().ok())
+    }
+    /// Get the track number as an integer, `None` for track numbers like `A3`
+    fn tracknumber_u32(&'a self) -> Option<u32> {
+        self.get_u32("TRACKNUMBER")
+    }
+    /// Get the total number of tracks, falls back to the total in a track number like `3/12`
+    fn totaltracks(&'a self) -> Option<&'a str> {
+        self.get("TOTALTRACKS")
+            .or_else(|| self.get("TRACKTOTAL"))
+            .or_else(|| self.get("TRACKNUMBER").and_then(denominator))
+    }
+    /// Get the disc number
+    ///
+    /// If the disc number is of the form `1/2`, only the `1` is returned
+    fn discnumber(&'a self) -> Option<&'a str> {
+        self.get("DISCNUMBER").map(numerator)
+    }
+    /// Get the disc number as an integer
+    fn discnumber_u32(&'a self) -> Option<u32> {
+        self.get_u32("DISCNUMBER")
+    }
+    /// Get the total number of discs, falls back to the total in a disc number like `1/2`
+    fn totaldiscs(&'a self) -> Option<&'a str> {
+        self.get("TOTALDISCS")
+            .or_else(|| self.get("DISCTOTAL"))
+            .or_else(|| self.get("DISCNUMBER").and_then(denominator))
+    }
+    /// Get the genre of a track
+    fn genre(&'a self) -> Option<&'a str> {
+        self.get("GENRE")
+    }
+    /// Get the release date of a track, falls back to the year if there is no date
+    fn date(&'a self) -> Option<&'a str> {
+        self.get("DATE").or_else(|| self.get("YEAR"))
+    }
+    /// Get the artist of the album the track is on
+    fn albumartist(&'a self) -> Option<&'a str> {
+        self.get("ALBUMARTIST").or_else(|| self.get("ALBUM ARTIST"))
+    }
+    /// Get the composer of a track
+    fn composer(&'a self) -> Option<&'a str> {
+        self.get("COMPOSER")
+    }
+    /// Get the comment on a track, falls back to the description if there is no comment
+    fn comment(&'a self) -> Option<&'a str> {
+        self.get("COMMENT").or_else(|| self.get("DESCRIPTION"))
+    }
+    /// Get the software that encoded the track, as set by the tagger in the `ENCODER` field
+    ///
+    /// This can differ from the vendor string of a Vorbis comment block, which is always written
+    /// by the library that wrote the comment block, like `reference libFLAC 1.4.3`.
+    fn encoder(&'a self) -> Option<&'a str> {
+        self.get("ENCODER")
+    }
+    /// Get the person or organization that encoded the track, falls back to `ENCODEDBY`
+    ///
+    /// Unlike `encoder`, this names who encoded the track rather than the software.
+    fn encoded_by(&'a self) -> Option<&'a str> {
+        self.get("ENCODED_BY").or_else(|| self.get("ENCODEDBY"))
+    }
+    /// Get the MusicBrainz recording ID of a track
+    fn musicbrainz_trackid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_TRACKID")
+    }
+    /// Get the MusicBrainz release ID of the album the track is on
+    fn musicbrainz_albumid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_ALBUMID")
+    }
+    /// Get the MusicBrainz artist ID of a track
+    fn musicbrainz_artistid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_ARTISTID")
+    }
+    /// Get the MusicBrainz artist ID of the artist of the album the track is on
+    fn musicbrainz_albumartistid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_ALBUMARTISTID")
+    }
+    /// Get the MusicBrainz release group ID of the album the track is on
+    fn musicbrainz_releasegroupid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_RELEASEGROUPID")
+    }
+    /// Get the MusicBrainz track ID, which identifies the track on a specific release
+    fn musicbrainz_releasetrackid(&'a self) -> Option<&'a str> {
+        self.get("MUSICBRAINZ_RELEASETRACKID")
+    }
+    /// Get the ReplayGain track gain in dB
+    fn replaygain_track_gain(&'a self) -> Option<f32> {
+        self.get("REPLAYGAIN_TRACK_GAIN").and_then(parse_gain)
+    }
+    /// Get the ReplayGain track peak
+    fn replaygain_track_peak(&'a self) -> Option<f32> {
+        self.get("REPLAYGAIN_TRACK_PEAK").and_then(parse_peak)
+    }
+    /// Get the ReplayGain album gain in dB
+    fn replaygain_album_gain(&'a self) -> Option<f32> {
+        self.get("REPLAYGAIN_ALBUM_GAIN").and_then(parse_gain)
+    }
+    /// Get the ReplayGain album peak
+    fn replaygain_album_peak(&'a self) -> Option<f32> {
+        self.get("REPLAYGAIN_ALBUM_PEAK").and_then(parse_peak)
+    }
+    /// Get all ReplayGain values, missing or malformed values are `None`
+    fn replaygain(&'a self) -> ReplayGain {
+        ReplayGain {
+            track_gain: self.replaygain_track_gain(),
+            track_peak: self.replaygain_track_peak(),
+            album_gain: self.replaygain_album_gain(),
+            album_peak: self.replaygain_album_peak(),
+        }
+    }
+    /// Get the R128 track gain in dB, relative to the -23 LUFS reference level of EBU R128
+    ///
+    /// This is used by Opus files instead of the ReplayGain track gain
+    fn r128_track_gain(&'a self) -> Option<f32> {
+        self.get("R128_TRACK_GAIN").and_then(parse_r128_gain)
+    }
+    /// Get the R128 album gain in dB, relative to the -23 LUFS reference level of EBU R128
+    fn r128_album_gain(&'a self) -> Option<f32> {
+        self.get("R128_ALBUM_GAIN").and_then(parse_r128_gain)
+    }
+    /// Get the value of a field that has no accessor of its own, like `RATING` or `ACOUSTID_ID`
+    ///
+    /// This is the same case-insensitive lookup as `get`, without consuming the music data like
+    /// `map` does.
+    fn custom(&'a self, key: &str) -> Option<&'a str> {
+        self.get(key)
+    }
+    /// Get the value of an arbitrary field
+    ///
+    /// A field with an empty value, like `KEY=`, is present, so this results in `Some("")`. By
+    /// default the fields from `iter` are searched, ignoring the case of the field names.
+    fn get(&'a self, key: &str) -> Option<&'a str> {
+        let key = normalize_key(key);
+        self.iter().find(|(k, _)| k.eq_ignore_ascii_case(&key)).map(|(_, value)| value)
+    }
+    /// Get the value of a numeric field as an integer, or `None` if it is not a number
+    ///
+    /// Of a value of the form `3/12`, only the `3` is returned
+    fn get_u32(&'a self, key: &str) -> Option<u32> {
+        self.get(key).map(numerator).and_then(|value| value.parse().ok())
+    }
+    /// Iterate over all fields and their values without consuming the music data
+    ///
+    /// By default only the title, artist and album are yielded, formats with other fields should
+    /// override this.
+    fn iter(&'a self) -> Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a> {
+        let fields = vec![("TITLE", self.title()), ("ARTIST", self.artist()), ("ALBUM", self.album())];
+        Box::new(fields.into_iter().filter_map(|(key, value)| value.map(|value| (key, value))))
+    }
     /// Get a map with all music data
-    fn map(self) -> HashMap<String, String>;
+    ///
+    /// The map is a `HashMap`, or a `BTreeMap` if the `std` feature is disabled.
+    fn map(self) -> Map<String, String>;
+    /// Convert the music data of any format into `MusicMetaData`, e.g. to handle FLAC and Ogg
+    /// files the same way
+    ///
+    /// The field names are normalized to uppercase, like those of parsed files.
+    fn into_music_metadata(self) -> MusicMetaData
+    where Self: Sized {
+        let map = self.map().into_iter().map(|(key, value)| (normalize_key(&key), value)).collect();
+        MusicMetaData { map }
+    }
+}
+
+/// Get the part before the slash of a value like `3/12`, or the whole value if there is no slash
+fn numerator(value: &str) -> &str {
+    value.split('/').next().unwrap_or(value).trim()
+}
+
+/// Splits a track number like `A3` into the alphabetic side letter and the rest
+fn split_side(value: &str) -> Option<(Option<char>, &str)> {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(side) if side.is_alphabetic() => Some((Some(side), chars.as_str().trim_start())),
+        Some(_) => Some((None, value)),
+        None => None,
+    }
+}
+
+/// Get the part after the slash of a value like `3/12`
+fn denominator(value: &str) -> Option<&str> {
+    value.split('/').nth(1).map(|x| x.trim())
+}
+
+/// Field names are case-insensitive and surrounding whitespace is ignored, so they are stored in
+/// uppercase without the whitespace
+fn normalize_key(key: &str) -> String {
+    key.trim().to_ascii_uppercase()
+}
+
+/// Joins a field name and value into a user comment, field names are stored in uppercase
+fn comment_line(key: &str, value: &str) -> String {
+    let mut line = normalize_key(key);
+    line.push('=');
+    line.push_str(value);
+    line
 }
 
 /// Represents a Vorbis comment block
-#[derive(Debug)]
+///
+/// Two blocks are equal if they have the same vendor string and the same values for every field,
+/// regardless of the order of the fields in the file.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(into = "VorbisFields", from = "VorbisFields"))]
 pub struct VorbisMetadata {
     vendor_string: String,
-    user_comments: HashMap<String, String>,
+    user_comments: Map<String, Vec<String>>,
+    /// The field names in the order of the file, with the index of the value in `user_comments`
+    /// and the undecoded value if decoding changed it
+    order: Vec<(String, usize, Option<Vec<u8>>)>,
+    warnings: Vec<String>,
+}
+
+impl VorbisMetadata {
+    /// Get the vendor string, which identifies the encoder that wrote the file
+    ///
+    /// The vendor string is not a user comment, so it is not part of the map returned by
+    /// `MusicData::map`
+    pub fn vendor(&self) -> &str {
+        &self.vendor_string
+    }
+
+    /// Get the problems that were skipped while parsing with the `tolerant` option
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Get all values of a field, in the order they appear in the file
+    ///
+    /// Vorbis comments may contain the same field multiple times, e.g. an `ARTIST` for every
+    /// artist of a collaboration. Field names are case-insensitive.
+    pub fn get_all(&self, key: &str) -> &[String] {
+        self.user_comments.get(&normalize_key(key)).map(|x| x.deref()).unwrap_or(&[])
+    }
+
+    /// Get the number of user comments, a field with multiple values counts once for every value
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns true if there are no user comments
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Returns true if the field has at least one value, field names are case-insensitive
+    pub fn contains(&self, key: &str) -> bool {
+        self.user_comments.contains_key(&normalize_key(key))
+    }
+
+    /// Iterate over the names of all fields, in the order they first appear in the file
+    ///
+    /// Every field name is yielded once, in uppercase
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        // Only the first value of every field has index 0
+        self.order.iter().filter(|(_, i, _)| *i == 0).map(|(key, _, _)| key.deref())
+    }
+
+    /// Iterate over all fields and their values, in the order they appear in the file
+    ///
+    /// Field names are in uppercase
+    pub fn iter(&self) -> Comments<'_> {
+        Comments { metadata: self, order: self.order.iter() }
+    }
+
+    /// Iterate over all fields and their undecoded values, in the order they appear in the file
+    ///
+    /// With the `lossy_utf8` option, values that are not valid UTF-8 can still be parsed, and
+    /// this returns their original bytes instead of the replacement characters. Likewise, a byte
+    /// order mark removed by the `strip_bom` option is still part of these bytes.
+    pub fn raw_comments(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.order.iter().map(move |(key, i, raw)| {
+            let value = match *raw {
+                Some(ref raw) => raw.deref(),
+                None => self.user_comments[key][*i].as_bytes(),
+            };
+            (key.deref(), value)
+        })
+    }
+
+    /// Get all fields as `KEY=value` lines, the form in which they are stored in the file
+    ///
+    /// The lines are in the order of the file
+    pub fn to_comment_lines(&self) -> Vec<String> {
+        self.iter().map(|(key, value)| comment_line(key, value)).collect()
+    }
+
+    /// Replace all values of a field, the new values take the place of the first old value
+    #[cfg(feature = "std")]
+    fn set_all(&mut self, key: &str, values: Vec<String>) {
+        let key = normalize_key(key);
+        let position = self.order.iter().position(|(k, _, _)| *k == key).unwrap_or(self.order.len());
+        self.order.retain(|(k, _, _)| *k != key);
+        self.order.splice(position..position, (0..values.len()).map(|i| (key.clone(), i, None)));
+        if values.is_empty() {
+            self.user_comments.remove(&key);
+        } else {
+            self.user_comments.insert(key, values);
+        }
+    }
+
+    /// Get the first value of a field
+    fn get_first(&self, key: &str) -> Option<&str> {
+        self.get_all(key).first().map(|x| x.deref())
+    }
+}
+
+impl IntoIterator for VorbisMetadata {
+    type Item = (String, String);
+    type IntoIter = vec::IntoIter<(String, String)>;
+
+    /// Iterate over all fields and their values, in the order they appear in the file
+    fn into_iter(mut self) -> vec::IntoIter<(String, String)> {
+        let mut comments = Vec::with_capacity(self.order.len());
+        for (key, i, _) in self.order {
+            if let Some(values) = self.user_comments.get_mut(&key) {
+                let value = mem::take(&mut values[i]);
+                comments.push((key, value));
+            }
+        }
+        comments.into_iter()
+    }
+}
+
+/// The serialized form of `VorbisMetadata`, the fields are kept in the order of the file
+///
+/// The index of every value is rebuilt when deserializing, so it can't point past the values.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct VorbisFields {
+    vendor: String,
+    comments: Vec<(String, String)>,
+    warnings: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+impl From<VorbisMetadata> for VorbisFields {
+    fn from(metadata: VorbisMetadata) -> VorbisFields {
+        let vendor = metadata.vendor_string.clone();
+        let warnings = metadata.warnings.clone();
+        VorbisFields { vendor, comments: metadata.into_iter().collect(), warnings }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<VorbisFields> for VorbisMetadata {
+    fn from(fields: VorbisFields) -> VorbisMetadata {
+        let mut user_comments = Map::new();
+        let mut order = Vec::with_capacity(fields.comments.len());
+        for (key, value) in fields.comments {
+            let key = normalize_key(&key);
+            let values = user_comments.entry(key.clone()).or_insert_with(Vec::new);
+            order.push((key, values.len(), None));
+            values.push(value);
+        }
+        VorbisMetadata { vendor_string: fields.vendor, user_comments, order, warnings: fields.warnings }
+    }
+}
+
+impl<'a> IntoIterator for &'a VorbisMetadata {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Comments<'a>;
+
+    fn into_iter(self) -> Comments<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the fields and values of a Vorbis comment block, in the order of the file
+pub struct Comments<'a> {
+    metadata: &'a VorbisMetadata,
+    order: slice::Iter<'a, (String, usize, Option<Vec<u8>>)>,
+}
+
+impl<'a> Iterator for Comments<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        self.order.next().map(|(key, i, _)| (key.deref(), self.metadata.user_comments[key][*i].deref()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+impl PartialEq for VorbisMetadata {
+    fn eq(&self, other: &VorbisMetadata) -> bool {
+        self.vendor_string == other.vendor_string && self.user_comments == other.user_comments
+    }
 }
 
 impl<'a> MusicData<'a> for VorbisMetadata {
     fn title(&'a self) -> Option<&'a str> {
-        self.user_comments.get("TITLE").map(|x| x.deref())
+        self.get_first("TITLE")
     }
     fn artist(&'a self) -> Option<&'a str> {
-        self.user_comments.get("ARTIST").map(|x| x.deref())
+        self.get_first("ARTIST")
     }
     fn album(&'a self) -> Option<&'a str> {
-        self.user_comments.get("ALBUM").map(|x| x.deref())
+        self.get_first("ALBUM")
     }
-    fn tracknumber(&'a self) -> Option<&'a str> {
-        self.user_comments.get("TRACKNUMBER").map(|x| x.deref())
+    fn get(&'a self, key: &str) -> Option<&'a str> {
+        self.get_first(key)
+    }
+    /// Iterate over all fields in the order of the file, repeated fields are yielded repeatedly
+    fn iter(&'a self) -> Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a> {
+        Box::new(VorbisMetadata::iter(self))
     }
-    fn map(self) -> HashMap<String, String> {
-        self.user_comments
+    /// Get a map with the first value of every field, use `get_all` to get repeated values
+    fn map(self) -> Map<String, String> {
+        // Fields without values are skipped instead of assuming the first value exists
+        self.user_comments.into_iter()
+            .filter_map(|(key, values)| values.into_iter().next().map(|value| (key, value)))
+            .collect()
+    }
+}
+
+impl fmt::Display for VorbisMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_tags(self, f)
+    }
+}
+
+/// Decodes the body of a VORBIS_COMMENT block with the default options, e.g. one that was taken
+/// from an Ogg packet
+impl<'a> TryFrom<&'a [u8]> for VorbisMetadata {
+    type Error = FlacError;
+
+    fn try_from(data: &'a [u8]) -> Result<VorbisMetadata> {
+        decode_vorbis_comments(data, &ParseOptions::default())
     }
 }
 
@@ -53,9 +555,66 @@ where M: MusicData<'a> {
     fn parse(&mut self) -> Result<M>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
 pub struct MusicMetaData {
-    map: HashMap<String, String>,
+    map: Map<String, String>,
+}
+
+impl MusicMetaData {
+    /// Create an empty set of fields, e.g. to build tags in memory
+    pub fn new() -> MusicMetaData {
+        MusicMetaData::default()
+    }
+
+    /// Set a field, replacing the previous value
+    ///
+    /// The field name is normalized to uppercase, like the field names of parsed files.
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where K: Into<String>, V: Into<String> {
+        self.map.insert(normalize_key(&key.into()), value.into());
+    }
+
+    /// Add the fields of another set, e.g. to combine the tags of a file with those of an online
+    /// lookup
+    ///
+    /// Fields that are present in both sets keep their current value, unless `overwrite` is true.
+    pub fn merge(&mut self, other: MusicMetaData, overwrite: bool) {
+        for (key, value) in other.map {
+            if overwrite || !self.map.contains_key(&key) {
+                self.map.insert(key, value);
+            }
+        }
+    }
+
+    /// Get the number of fields
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns true if the field is present, field names are case-insensitive
+    pub fn contains(&self, key: &str) -> bool {
+        self.map.contains_key(&normalize_key(key))
+    }
+
+    /// Iterate over the names of all fields, in uppercase
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.map.keys().map(|key| key.deref())
+    }
+
+    /// Get all fields as `KEY=value` lines, which can be written to a VORBIS_COMMENT block
+    ///
+    /// The lines are sorted by field name
+    pub fn to_comment_lines(&self) -> Vec<String> {
+        let mut fields: Vec<(&String, &String)> = self.map.iter().collect();
+        fields.sort();
+        fields.into_iter().map(|(key, value)| comment_line(key, value)).collect()
+    }
 }
 
 impl<'a> MusicData<'a> for MusicMetaData {
@@ -68,117 +627,252 @@ impl<'a> MusicData<'a> for MusicMetaData {
     fn album(&'a self) -> Option<&'a str> {
         self.map.get("ALBUM").map(|x| x.deref())
     }
-    fn tracknumber(&'a self) -> Option<&'a str> {
-        self.map.get("TRACKNUMBER").map(|x| x.deref())
+    fn get(&'a self, key: &str) -> Option<&'a str> {
+        self.map.get(&normalize_key(key)).map(|x| x.deref())
+    }
+    fn iter(&'a self) -> Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a> {
+        Box::new(self.map.iter().map(|(key, value)| (key.deref(), value.deref())))
     }
-    fn map(self) -> HashMap<String, String> {
+    fn map(self) -> Map<String, String> {
         self.map
     }
 }
 
-impl<'a> From<VorbisMetadata> for MusicMetaData {
-    fn from(c: VorbisMetadata) -> Self {
-        MusicMetaData {map: c.map()}
+impl IntoIterator for MusicMetaData {
+    type Item = (String, String);
+    type IntoIter = <Map<String, String> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
     }
 }
 
-pub struct FlacParser<'a, R> 
-where R: 'a + Read + BufRead {
-    file: &'a mut R,
+impl fmt::Display for MusicMetaData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_tags(self, f)
+    }
 }
 
-impl<'a, R> FlacParser<'a, R>
-where R: Read + BufRead {
-    pub fn new(file: &'a mut R) -> Result<FlacParser<R>> {
-        if is_flac_file(file.by_ref())? {
-            Ok(FlacParser{file: file})
-        } else {
-            Err(Error::new(ErrorKind::InvalidData, "could not parse as a flac file"))
+/// Writes the well-known fields of the metadata as aligned lines, missing fields are left out
+fn write_tags<'a, M>(metadata: &'a M, f: &mut fmt::Formatter) -> fmt::Result
+where M: MusicData<'a> {
+    let fields = [
+        ("Title", metadata.title(), None),
+        ("Artist", metadata.artist(), None),
+        ("Album", metadata.album(), None),
+        ("Album artist", metadata.albumartist(), None),
+        ("Track", metadata.tracknumber(), metadata.totaltracks()),
+        ("Disc", metadata.discnumber(), metadata.totaldiscs()),
+        ("Date", metadata.date(), None),
+        ("Genre", metadata.genre(), None),
+        ("Composer", metadata.composer(), None),
+        ("Comment", metadata.comment(), None),
+    ];
+    let mut first = true;
+    for &(label, value, total) in fields.iter() {
+        let value = match value {
+            Some(value) if !value.is_empty() => value,
+            _ => continue,
+        };
+        if !first {
+            writeln!(f)?;
+        }
+        first = false;
+        // Align the values after the longest label, "Album artist"
+        write!(f, "{}:{:width$} {}", label, "", value, width = 12 - label.len())?;
+        if let Some(total) = total {
+            write!(f, "/{}", total)?;
         }
     }
+    Ok(())
 }
 
-impl<'a, 'b, R> MusicDataParser<'a, VorbisMetadata> for FlacParser<'b, R>
-where R: Read + BufRead {
-    fn parse(&mut self) -> Result<VorbisMetadata> {
-        search_comment_block(self.file)
+impl From<VorbisMetadata> for MusicMetaData {
+    fn from(c: VorbisMetadata) -> Self {
+        MusicMetaData {map: c.map()}
     }
 }
 
-pub fn parse<'a, R>(file: &mut R) -> Result<MusicMetaData>
-where R: Read + BufRead {
-    if let Ok(mut fp) = FlacParser::new(file) {
-        fp.parse().map(|x| x.into())
-    } else {
-        Err(Error::new(ErrorKind::InvalidData, "could not parse any metadata"))
+impl<'a> From<BorrowedVorbisMetadata<'a>> for MusicMetaData {
+    fn from(c: BorrowedVorbisMetadata<'a>) -> Self {
+        MusicMetaData {map: c.map()}
     }
 }
 
-/// Returns true if the reader is a FLAC file
-fn is_flac_file<R>(file: &mut R) -> Result<bool>
-where R: Read {
-    let mut buffer = [0; 4];
-    file.read_exact(&mut buffer)?;
-    Ok(buffer == "fLaC".as_bytes())
+/// Parses the metadata of a FLAC file that is completely in memory
+///
+/// Unlike `parse` this does not need `std::io`, so it is also available without the `std`
+/// feature.
+pub fn parse_bytes(data: &[u8]) -> Result<MusicMetaData> {
+    decode_vorbis_comments(comment_block(data)?, &ParseOptions::default()).map(|x| x.into())
 }
 
-/// Searches for a vorbis comment block in the metadata blocks of a flac file
-/// 
-/// This function assumes that the first 4 bytes of the flac file have been consumed
-fn search_comment_block<R>(file: &mut R) -> Result<VorbisMetadata>
-where R: Read + BufRead {
+/// Finds the body of the first VORBIS_COMMENT block of a FLAC file that is completely in memory
+pub(crate) fn comment_block(data: &[u8]) -> Result<&[u8]> {
+    let mut reader = SliceReader::new(data);
+    if reader.read_bytes(4)? != b"fLaC" {
+        return Err(FlacError::NotFlac);
+    }
     loop {
-        let (last, blocktype, size) = {
-            let mut block_header_buf = [0; 4];
-            file.read_exact(&mut block_header_buf)?;
-            let block_header = block_header_buf[0];
-            block_header_buf[0] = 0;
-            (block_header >> 7 == 1, block_header & 0b0111111, BigEndian::read_u32(&block_header_buf))
-        };
-        if last {
-            return Err(Error::new(ErrorKind::UnexpectedEof, "no comment block"));
+        let mut header_bytes = [0; 4];
+        header_bytes.copy_from_slice(reader.read_bytes(4)?);
+        let header = decode_block_header(&header_bytes);
+        // A length that doesn't fit in usize can't fit in the data either
+        let body = usize::try_from(header.length).ok().and_then(|length| reader.read_bytes(length).ok());
+        let body = body.ok_or(FlacError::MalformedBlock {
+            block_type: header.block_type,
+            declared_size: header.length,
+        })?;
+        if header.block_type == 4 {
+            return Ok(body);
         }
-        if blocktype == 4 {
-            return parse_vorbis_comments(file.by_ref());
+        if header.is_last {
+            return Err(FlacError::NoCommentBlock);
         }
-        file.consume(size as usize);
     }
 }
 
-/// Parses vorbis comments if the reader is positioned at the start of the comment block
-fn parse_vorbis_comments<R>(file: &mut R) -> Result<VorbisMetadata> 
-where R: Read {
-    // Vorbis comments support vendor strings
-    let vendor_string = {
-        let length = file.read_u32::<LittleEndian>()?;
-        read_n(file.by_ref(), length as u64)?
-    };
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    extern crate serde_json;
 
-    let ncomments = file.read_u32::<LittleEndian>()?;
-    let mut comments = HashMap::new();
+    use super::*;
+    use crate::test_util::{block, comments, flac, stream_info};
 
-    // Read all the lines into a map
-    for _ in 0..ncomments {
-        let length = file.read_u32::<LittleEndian>()?;
+    /// Implements only the required methods, like a format outside of this crate would
+    struct Mock;
 
-        let mut split: Vec<String> = read_n(file.by_ref(), length as u64)?.split('=').map(|x| x.to_string()).collect();
+    impl<'a> MusicData<'a> for Mock {
+        fn title(&'a self) -> Option<&'a str> {
+            Some("Get Got")
+        }
+        fn artist(&'a self) -> Option<&'a str> {
+            Some("Death Grips")
+        }
+        fn album(&'a self) -> Option<&'a str> {
+            None
+        }
+        fn map(self) -> Map<String, String> {
+            let mut map = Map::new();
+            map.insert("title".into(), "Get Got".into());
+            map.insert("Artist".into(), "Death Grips".into());
+            map
+        }
+    }
 
-        // If this assertion fails, the flac file is malformed
-        if split.len() != 2 {
-            return Err(Error::new(ErrorKind::InvalidData, "malformed FLAC file, could not split user comment"));
+    fn metadata(fields: &[(&str, &str)]) -> MusicMetaData {
+        let mut metadata = MusicMetaData::new();
+        for &(key, value) in fields {
+            metadata.insert(key, value);
         }
-        comments.insert(split.remove(0), split.remove(0));
+        metadata
     }
 
-    Ok(VorbisMetadata{vendor_string: vendor_string, user_comments: comments})
-}
+    #[test]
+    fn get_defaults_to_the_required_accessors() {
+        assert_eq!(Mock.get("title"), Some("Get Got"));
+        assert_eq!(Mock.get("ARTIST"), Some("Death Grips"));
+        assert_eq!(Mock.get("ALBUM"), None);
+        assert_eq!(Mock.tracknumber(), None);
+        assert_eq!(Mock.genre(), None);
+        let fields: Vec<(&str, &str)> = Mock.iter().collect();
+        assert_eq!(fields, [("TITLE", "Get Got"), ("ARTIST", "Death Grips")]);
+    }
+
+    #[test]
+    fn genre_composer_and_date() {
+        let tags = metadata(&[("GENRE", "Rap"), ("COMPOSER", "Zach Hill"), ("DATE", "2012-04-24"), ("YEAR", "2012")]);
+        assert_eq!(tags.genre(), Some("Rap"));
+        assert_eq!(tags.composer(), Some("Zach Hill"));
+        assert_eq!(tags.date(), Some("2012-04-24"));
+        assert_eq!(metadata(&[("YEAR", "2012")]).date(), Some("2012"));
+    }
 
-/// Read n bytes from the reader and construct it into a string
-fn read_n<R>(reader: R, bytes_to_read: u64) -> Result<String>
-where R: Read {
-    let mut buf = String::new();
-    let mut chunk = reader.take(bytes_to_read);
-    let n = chunk.read_to_string(&mut buf)?;
-    assert_eq!(bytes_to_read as usize, n);
-    Ok(buf)
+    #[test]
+    fn albumartist_accepts_both_spellings() {
+        assert_eq!(metadata(&[("ALBUMARTIST", "a"), ("ALBUM ARTIST", "b")]).albumartist(), Some("a"));
+        assert_eq!(metadata(&[("album artist", "b")]).albumartist(), Some("b"));
+        assert_eq!(metadata(&[]).albumartist(), None);
+    }
+
+    #[test]
+    fn comment_falls_back_to_description() {
+        assert_eq!(metadata(&[("COMMENT", "a"), ("DESCRIPTION", "b")]).comment(), Some("a"));
+        assert_eq!(metadata(&[("DESCRIPTION", "b")]).comment(), Some("b"));
+    }
+
+    /// A file with a STREAMINFO block and a comment block with the given title, which is the last
+    /// block
+    fn tagged(title: &str) -> Vec<u8> {
+        flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", title)]))])
+    }
+
+    #[test]
+    fn parse_bytes_reads_the_comments() {
+        let metadata = parse_bytes(&tagged("Get Got")).unwrap();
+        assert_eq!(metadata.title(), Some("Get Got"));
+        assert_eq!(metadata.len(), 1);
+    }
+
+    #[test]
+    fn parse_bytes_rejects_other_files() {
+        match parse_bytes(b"ID3\x04\x00\x00\x00\x00\x00\x00") {
+            Err(FlacError::NotFlac) => (),
+            other => panic!("expected NotFlac, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_bytes_without_comments() {
+        let mut file = tagged("x");
+        // Set the last-block flag on the STREAMINFO block and drop the comment block
+        file[4] = 0x80;
+        file.truncate(4 + 4 + 34);
+        match parse_bytes(&file) {
+            Err(FlacError::NoCommentBlock) => (),
+            other => panic!("expected NoCommentBlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_bytes_of_a_truncated_file() {
+        let file = tagged("Get Got");
+        match parse_bytes(&file[..file.len() - 1]) {
+            Err(FlacError::MalformedBlock { block_type: 4, .. }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn into_music_metadata_of_another_format() {
+        let metadata = Mock.into_music_metadata();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata.title(), Some("Get Got"));
+        assert_eq!(metadata.artist(), Some("Death Grips"));
+        let mut keys: Vec<&str> = metadata.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["ARTIST", "TITLE"]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn music_metadata_json_round_trip() {
+        let metadata = parse_bytes(&tagged("Get Got")).unwrap();
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(json, r#"{"TITLE":"Get Got"}"#);
+        assert_eq!(serde_json::from_str::<MusicMetaData>(&json).unwrap(), metadata);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn vorbis_metadata_json_round_trip() {
+        let data = comments(&[("ARTIST", "a"), ("TITLE", "t"), ("ARTIST", "b")]);
+        let metadata = VorbisMetadata::try_from(&data[..]).unwrap();
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(json, r#"{"vendor":"test","comments":[["ARTIST","a"],["TITLE","t"],["ARTIST","b"]],"warnings":[]}"#);
+        let parsed: VorbisMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, metadata);
+        assert_eq!(parsed.to_comment_lines(), metadata.to_comment_lines());
+    }
 }
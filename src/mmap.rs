@@ -0,0 +1,55 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{comment_block, parse_borrowed, MusicMetaData, Result};
+
+/// Maps the file at the given path into memory and parses its metadata, like `parse_path`
+///
+/// The comment block is parsed with `parse_borrowed` straight from the mapping, only the resulting
+/// fields are copied out before the file is unmapped. Invalid UTF-8 results in
+/// `FlacError::InvalidUtf8`, like it does for `parse_borrowed`.
+pub fn parse_mmap<P>(path: P) -> Result<MusicMetaData>
+where P: AsRef<Path> {
+    let file = File::open(path)?;
+    // Safety: the mapping is only read within this function, but another process truncating or
+    // writing the file in the meantime can still change the data or crash the process
+    let data = unsafe { Mmap::map(&file)? };
+    parse_borrowed(comment_block(&data)?).map(|x| x.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+    use crate::{parse_path, FlacError, MusicData};
+    use crate::test_util::{block, comments, flac, stream_info};
+
+    #[test]
+    fn same_fields_as_parse_path() {
+        let path = env::temp_dir().join(format!("flacparse-{}-mmap.flac", process::id()));
+        fs::write(&path, flac(&[
+            block(0, false, &stream_info()),
+            block(1, false, &[0; 4096]),
+            block(4, true, &comments(&[("TITLE", "Get Got"), ("artist", "Death Grips"), ("ARTIST", "Zach Hill")])),
+        ])).unwrap();
+        let mapped = parse_mmap(&path).unwrap();
+        let read = parse_path(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(mapped, read);
+        assert_eq!(mapped.get("ARTIST"), Some("Death Grips"));
+    }
+
+    #[test]
+    fn not_a_flac_file() {
+        let path = env::temp_dir().join(format!("flacparse-{}-mmap.txt", process::id()));
+        fs::write(&path, b"not a flac file").unwrap();
+        let result = parse_mmap(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(FlacError::NotFlac) => (),
+            other => panic!("expected NotFlac, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::VecDeque;
+use std::io::prelude::*;
+
+use crate::{FlacError, MusicDataParser, ParseOptions, Result, VorbisMetadata};
+use crate::bytes::{decode_block_header, decode_vorbis_comments, parse_vorbis_comments_with_framing};
+
+/// Parses the Vorbis comments of an Ogg Vorbis, Ogg Opus or Ogg FLAC file
+///
+/// Only the first logical stream of the file is read, multiplexed streams are not supported.
+pub struct OggParser<R>
+where R: Read {
+    file: R,
+    options: ParseOptions,
+    /// The lacing values of the current page that have not been read yet
+    lacing: VecDeque<u8>,
+}
+
+impl<R> OggParser<R>
+where R: Read {
+    /// Create a parser for a reader, which can also be a mutable reference to a reader
+    pub fn new(file: R) -> Result<OggParser<R>> {
+        OggParser::with_options(file, ParseOptions::default())
+    }
+
+    /// Create a parser with the given options
+    pub fn with_options(file: R, options: ParseOptions) -> Result<OggParser<R>> {
+        let mut parser = OggParser { file, options, lacing: VecDeque::new() };
+        parser.read_page_header()?;
+        Ok(parser)
+    }
+
+    /// Reads the header of the next page, if the reader is positioned at the start of a page
+    fn read_page_header(&mut self) -> Result<()> {
+        // Capture pattern, version, header type, granule position, serial number, page sequence
+        // number, checksum and the number of segments
+        let mut header = [0; 27];
+        self.file.read_exact(&mut header)?;
+        if &header[..4] != b"OggS" {
+            return Err(FlacError::NotOgg);
+        }
+        let mut lacing = vec![0; header[26] as usize];
+        self.file.read_exact(&mut lacing)?;
+        self.lacing = lacing.into();
+        Ok(())
+    }
+
+    /// Reads the next packet, which may continue on the following pages
+    fn read_packet(&mut self) -> Result<Vec<u8>> {
+        let mut packet = Vec::new();
+        loop {
+            while let Some(length) = self.lacing.pop_front() {
+                let start = packet.len();
+                packet.resize(start + length as usize, 0);
+                self.file.read_exact(&mut packet[start..])?;
+                if packet.len() > self.options.max_comment_size {
+                    return Err(FlacError::CommentTooLarge);
+                }
+                // A segment shorter than 255 bytes ends the packet
+                if length < 255 {
+                    return Ok(packet);
+                }
+            }
+            self.read_page_header()?;
+        }
+    }
+
+    /// Parses the comments of Ogg FLAC, in which every header packet after the first one holds
+    /// a single metadata block
+    fn parse_flac(&mut self) -> Result<VorbisMetadata> {
+        loop {
+            let packet = self.read_packet()?;
+            if packet.len() < 4 {
+                return Err(FlacError::Truncated);
+            }
+            let mut header = [0; 4];
+            header.copy_from_slice(&packet[..4]);
+            let header = decode_block_header(&header);
+            if header.block_type == 4 {
+                return decode_vorbis_comments(&packet[4..], &self.options);
+            }
+            if header.is_last {
+                return Err(FlacError::NoCommentBlock);
+            }
+        }
+    }
+}
+
+impl<'a, R> MusicDataParser<'a, VorbisMetadata> for OggParser<R>
+where R: Read {
+    fn parse(&mut self) -> Result<VorbisMetadata> {
+        // The identification header is the first packet, the comment header the second one
+        let identification = self.read_packet()?;
+        if identification.starts_with(b"\x7FFLAC") {
+            return self.parse_flac();
+        }
+        let magic: &[u8] = if identification.starts_with(b"\x01vorbis") {
+            b"\x03vorbis"
+        } else if identification.starts_with(b"OpusHead") {
+            b"OpusTags"
+        } else {
+            return Err(FlacError::Unsupported("Ogg streams other than Vorbis, Opus and FLAC"));
+        };
+
+        let comments = self.read_packet()?;
+        if !comments.starts_with(magic) {
+            return Err(FlacError::NoCommentBlock);
+        }
+        let data = &comments[magic.len()..];
+        if magic == b"\x03vorbis" {
+            // Only Vorbis ends the comment header with a framing bit, which is checked in strict mode
+            parse_vorbis_comments_with_framing(data, &self.options).map(|(comments, _)| comments)
+        } else {
+            decode_vorbis_comments(data, &self.options)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FlacParser, MusicData};
+    use crate::test_util::{block, comments, ogg_packets, ogg_page, stream_info};
+
+    /// The comment header of Ogg Vorbis, which ends with a framing bit
+    fn vorbis_comments(fields: &[(&str, &str)]) -> Vec<u8> {
+        let mut packet = b"\x03vorbis".to_vec();
+        packet.extend(comments(fields));
+        packet.push(1);
+        packet
+    }
+
+    #[test]
+    fn ogg_vorbis() {
+        let file = ogg_packets(&[b"\x01vorbis\x00\x00\x00\x00", &vorbis_comments(&[("TITLE", "Get Got")])]);
+        let metadata = OggParser::new(&file[..]).unwrap().parse().unwrap();
+        assert_eq!(metadata.title(), Some("Get Got"));
+        assert_eq!(metadata.vendor(), "test");
+    }
+
+    #[test]
+    fn ogg_opus() {
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend(comments(&[("TITLE", "Get Got"), ("R128_TRACK_GAIN", "-256")]));
+        let file = ogg_packets(&[b"OpusHead\x01\x02", &tags]);
+        let metadata = OggParser::new(&file[..]).unwrap().parse().unwrap();
+        assert_eq!(metadata.title(), Some("Get Got"));
+        assert_eq!(metadata.r128_track_gain(), Some(-1.0));
+    }
+
+    #[test]
+    fn comment_packet_spans_two_pages() {
+        let identification = b"\x01vorbis\x00\x00\x00\x00";
+        let long_title = "a".repeat(400);
+        let packet = vorbis_comments(&[("TITLE", &long_title)]);
+        assert!(packet.len() > 255 && packet.len() < 510);
+        // The first page ends with a full segment, so the packet continues on the next page
+        let mut first = identification.to_vec();
+        first.extend_from_slice(&packet[..255]);
+        let mut file = ogg_page(&[identification.len() as u8, 255], &first);
+        file.extend(ogg_page(&[(packet.len() - 255) as u8], &packet[255..]));
+        let metadata = OggParser::new(&file[..]).unwrap().parse().unwrap();
+        assert_eq!(metadata.title(), Some(&long_title[..]));
+    }
+
+    #[test]
+    fn page_with_garbage() {
+        let mut file = b"OggS\x00\x02".to_vec();
+        file.extend_from_slice(&[0xFF; 40]);
+        assert!(OggParser::new(&file[..]).is_err());
+    }
+
+    #[test]
+    fn ogg_flac() {
+        let mut identification = b"\x7FFLAC\x01\x00\x00\x01fLaC".to_vec();
+        identification.extend(block(0, false, &stream_info()));
+        let file = ogg_packets(&[&identification, &block(1, false, &[0; 8]), &block(4, true, &comments(&[("TITLE", "Get Got")]))]);
+        let metadata = OggParser::new(&file[..]).unwrap().parse().unwrap();
+        assert_eq!(metadata.title(), Some("Get Got"));
+    }
+
+    #[test]
+    fn ogg_flac_is_not_native_flac() {
+        let identification = b"\x7FFLAC\x01\x00\x00\x01fLaC";
+        let file = ogg_packets(&[identification]);
+        match FlacParser::new(&file[..]) {
+            Err(FlacError::OggEncapsulated) => (),
+            other => panic!("expected OggEncapsulated, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn unframed_vorbis_comments_in_strict_mode() {
+        let mut packet = vorbis_comments(&[("TITLE", "Get Got")]);
+        packet.pop();
+        let file = ogg_packets(&[b"\x01vorbis\x00\x00\x00\x00", &packet]);
+        assert!(OggParser::new(&file[..]).unwrap().parse().is_ok());
+        let strict = ParseOptions::new().strict(true);
+        assert!(matches!(OggParser::with_options(&file[..], strict).unwrap().parse(), Err(FlacError::InvalidStructure(_))));
+        let file = ogg_packets(&[b"\x01vorbis\x00\x00\x00\x00", &vorbis_comments(&[("TITLE", "Get Got")])]);
+        assert!(OggParser::with_options(&file[..], strict).unwrap().parse().is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+/// Options that change how lenient the parser is
+///
+/// The default options reject data that can't be parsed, but don't validate the structure of the
+/// file. Options are set with chained calls, like `ParseOptions::new().lossy_utf8(true)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) lossy_utf8: bool,
+    pub(crate) tolerant_id3: bool,
+    pub(crate) collect_pictures: bool,
+    pub(crate) max_comment_size: usize,
+    pub(crate) max_total_comments_bytes: usize,
+    pub(crate) strict: bool,
+    pub(crate) tolerant: bool,
+    pub(crate) strip_bom: bool,
+}
+
+impl ParseOptions {
+    /// Create the default, strict options
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            lossy_utf8: false,
+            tolerant_id3: false,
+            collect_pictures: true,
+            max_comment_size: 16 * 1024 * 1024,
+            max_total_comments_bytes: 16 * 1024 * 1024,
+            strict: false,
+            tolerant: false,
+            strip_bom: false,
+        }
+    }
+
+    /// Replace invalid UTF-8 in strings with U+FFFD instead of failing with
+    /// `FlacError::InvalidUtf8`
+    pub fn lossy_utf8(mut self, lossy_utf8: bool) -> ParseOptions {
+        self.lossy_utf8 = lossy_utf8;
+        self
+    }
+
+    /// Accept files with an ID3v2 tag in front of the `fLaC` marker
+    pub fn tolerant_id3(mut self, tolerant_id3: bool) -> ParseOptions {
+        self.tolerant_id3 = tolerant_id3;
+        self
+    }
+
+    /// Read the image data of PICTURE blocks, enabled by default
+    ///
+    /// When disabled the image data is skipped, so the `data` of every picture is empty. This
+    /// saves memory when only the other fields of the pictures are needed.
+    pub fn collect_pictures(mut self, collect_pictures: bool) -> ParseOptions {
+        self.collect_pictures = collect_pictures;
+        self
+    }
+
+    /// Validate the order of the metadata blocks, which results in `FlacError::InvalidStructure`
+    /// if STREAMINFO is not the first block or if there is more than one VORBIS_COMMENT block
+    ///
+    /// Field names are validated as well, field names with characters outside of the ASCII
+    /// range 0x20 to 0x7D result in `FlacError::InvalidKey`.
+    pub fn strict(mut self, strict: bool) -> ParseOptions {
+        self.strict = strict;
+        self
+    }
+
+    /// Skip malformed user comments instead of failing, the skipped problems are available from
+    /// `VorbisMetadata::warnings`
+    ///
+    /// Comment lengths that buggy encoders wrote as big-endian are recovered as well, and a file
+    /// that ends without a metadata block with the last-block flag set is treated as if the
+    /// metadata ends there.
+    pub fn tolerant(mut self, tolerant: bool) -> ParseOptions {
+        self.tolerant = tolerant;
+        self
+    }
+
+    /// Remove a byte order mark (U+FEFF) from the start of comment values, which some Windows
+    /// taggers write
+    ///
+    /// Disabled by default, so values are returned exactly as they are stored.
+    pub fn strip_bom(mut self, strip_bom: bool) -> ParseOptions {
+        self.strip_bom = strip_bom;
+        self
+    }
+
+    /// The largest comment block, and the largest single comment, that is read, 16 MiB by default
+    ///
+    /// Larger comments result in `FlacError::CommentTooLarge`, which protects against files that
+    /// claim huge lengths to exhaust memory.
+    pub fn max_comment_size(mut self, max_comment_size: usize) -> ParseOptions {
+        self.max_comment_size = max_comment_size;
+        self
+    }
+
+    /// The largest total length of all user comments together, 16 MiB by default
+    ///
+    /// Unlike `max_comment_size`, this also limits many comments that are each within the limit.
+    /// Comments that are larger together result in `FlacError::CommentTotalTooLarge`.
+    pub fn max_total_comments_bytes(mut self, max_total_comments_bytes: usize) -> ParseOptions {
+        self.max_total_comments_bytes = max_total_comments_bytes;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions::new()
+    }
+}
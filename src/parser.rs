@@ -0,0 +1,1187 @@
+use std::io;
+use std::io::{Error, ErrorKind, SeekFrom};
+use std::io::prelude::*;
+use std::fs::File;
+use std::path::Path;
+use std::str;
+
+use crate::{FlacError, MusicDataParser, MusicMetaData, ParseOptions, Result, VorbisMetadata};
+use crate::application::{parse_application, Application};
+use crate::blocks::{Block, BlockEntry, FlacMetadata, FlacSummary, MetadataBlocks, ParsedFile};
+use crate::bytes::{decode_block_header, decode_comment, decode_string, decode_vorbis_comments, split_vorbis_comments, BlockHeader};
+use crate::cuesheet::{parse_cue_sheet, CueSheet};
+use crate::ogg::OggParser;
+use crate::picture::{parse_picture, Picture, PictureType};
+use crate::seektable::{parse_seek_table, SeekPoint};
+use crate::streaminfo::{parse_stream_info, Md5Status, StreamInfo};
+
+/// Parses the metadata blocks of a FLAC file
+///
+/// The metadata blocks are read front to back, so every method continues where the previous one
+/// stopped. The parser never reads past the block with the last-block flag set, which means the
+/// audio frames are never touched.
+///
+/// Blocks that are not needed are skipped by reading them, unless the parser was created with
+/// `FlacParser::seekable`.
+pub struct FlacParser<R> 
+where R: Read + BufRead {
+    file: R,
+    state: ReadState,
+    options: ParseOptions,
+    /// Skips the body of a block, by reading it or by seeking past it
+    skip: fn(&mut R, &BlockHeader) -> Result<()>,
+    /// The length of the reader from where the parser started, only known if it can seek
+    length: Option<u64>,
+}
+
+impl<R> FlacParser<R>
+where R: Read + BufRead {
+    /// Create a parser for a reader, which can also be a mutable reference to a reader
+    pub fn new(file: R) -> Result<FlacParser<R>> {
+        FlacParser::with_options(file, ParseOptions::default())
+    }
+
+    /// Create a parser that also accepts files with an ID3v2 tag in front of the `fLaC` marker
+    ///
+    /// Such files don't follow the specification, but some taggers produce them anyway. The other
+    /// problems that the `tolerant` option skips are accepted as well.
+    pub fn tolerant(file: R) -> Result<FlacParser<R>> {
+        FlacParser::with_options(file, ParseOptions::new().tolerant_id3(true).tolerant(true))
+    }
+
+    /// Create a parser with the given options
+    pub fn with_options(mut file: R, options: ParseOptions) -> Result<FlacParser<R>> {
+        match find_flac_marker(file.by_ref(), options.tolerant_id3)? {
+            Some(marker) => Ok(FlacParser {
+                file,
+                state: ReadState::new(marker + 4),
+                options,
+                skip: |file, header| skip_body(file, header),
+                length: None,
+            }),
+            None => Err(FlacError::NotFlac),
+        }
+    }
+
+    /// Parse the STREAMINFO block, which describes the properties of the audio stream
+    ///
+    /// The STREAMINFO block is always the first metadata block, so this should be called before
+    /// any other blocks are read.
+    pub fn stream_info(&mut self) -> Result<StreamInfo> {
+        match self.search_block(0)? {
+            Some(header) => parse_body(self.file.by_ref(), &header, parse_stream_info),
+            None => Err(FlacError::NoStreamInfo),
+        }
+    }
+
+    /// Parse only the first metadata block, which has to be the STREAMINFO block
+    ///
+    /// Unlike `stream_info`, no other blocks are skipped in search of the STREAMINFO block, so
+    /// this is the fastest way to get the properties of the audio stream. If the first block is
+    /// not a STREAMINFO block, this results in `FlacError::InvalidStructure`.
+    pub fn stream_info_only(&mut self) -> Result<StreamInfo> {
+        let header = self.next_header()?.ok_or(FlacError::NoStreamInfo)?;
+        if header.block_type != 0 {
+            return Err(FlacError::InvalidStructure("STREAMINFO must be the first metadata block"));
+        }
+        parse_body(self.file.by_ref(), &header, parse_stream_info)
+    }
+
+    /// Check the STREAMINFO block for an MD5 signature of the audio
+    ///
+    /// An all-zero signature results in `Md5Status::Unknown`, the audio of such a file can't be
+    /// verified. The audio frames are not decoded, so a stored signature is never compared to the
+    /// audio and results in `Md5Status::Present`.
+    pub fn verify_md5(&mut self) -> Result<Md5Status> {
+        Ok(if self.stream_info()?.has_md5() { Md5Status::Present } else { Md5Status::Unknown })
+    }
+
+    /// Iterate over all remaining metadata blocks
+    pub fn blocks<'p>(&'p mut self) -> MetadataBlocks<'p, R> {
+        MetadataBlocks::new(&mut self.file, &mut self.state, self.options)
+    }
+
+    /// Parse all remaining metadata blocks in a single pass
+    ///
+    /// Blocks that were already read by other methods are not part of the result.
+    pub fn parse_all(&mut self) -> Result<FlacMetadata> {
+        let mut metadata = FlacMetadata::default();
+        for block in self.blocks() {
+            match block? {
+                Block::StreamInfo(stream_info) => metadata.stream_info = Some(stream_info),
+                Block::VorbisComment(comments) => metadata.comments = Some(comments),
+                Block::Picture(picture) => metadata.pictures.push(picture),
+                Block::SeekTable(seek_table) => metadata.seek_table = seek_table,
+                Block::Application(application) => metadata.applications.push(application),
+                Block::CueSheet(cue_sheet) => metadata.cue_sheet = Some(cue_sheet),
+                Block::Padding(_) | Block::Unknown { .. } => (),
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Parse the SEEKTABLE block, the seek table is empty if the file has no SEEKTABLE block
+    pub fn seek_table(&mut self) -> Result<Vec<SeekPoint>> {
+        match self.search_block(3)? {
+            Some(header) => parse_body(self.file.by_ref(), &header, |body| parse_seek_table(body, header.length)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Parse all APPLICATION blocks, which hold data of third party applications
+    pub fn applications(&mut self) -> Result<Vec<Application>> {
+        let mut applications = Vec::new();
+        while let Some(header) = self.search_block(2)? {
+            applications.push(parse_body(self.file.by_ref(), &header, |body| parse_application(body, header.length))?);
+        }
+        Ok(applications)
+    }
+
+    /// Parse the CUESHEET block, if the file has one
+    pub fn cue_sheet(&mut self) -> Result<Option<CueSheet>> {
+        let options = self.options;
+        match self.search_block(5)? {
+            Some(header) => parse_body(self.file.by_ref(), &header, |body| parse_cue_sheet(body, &options)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the VORBIS_COMMENT block, which is `None` for a valid file without tags
+    ///
+    /// Unlike `parse`, a missing comment block is not an error.
+    pub fn comments(&mut self) -> Result<Option<VorbisMetadata>> {
+        let options = self.options;
+        match self.search_block(4)? {
+            Some(header) => parse_body(self.file.by_ref(), &header, |body| parse_vorbis_comments(body, &options)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Call a function with the field name and value of every user comment, without collecting
+    /// them in a map
+    ///
+    /// The field names are in uppercase, like the keys of the map. Returns false if the file has
+    /// no VORBIS_COMMENT block.
+    pub fn for_each_comment<F>(&mut self, mut f: F) -> Result<bool>
+    where F: FnMut(&str, &str) {
+        let options = self.options;
+        match self.search_block(4)? {
+            Some(header) => parse_body(self.file.by_ref(), &header, |body| {
+                let data = read_comment_block(body, &options)?;
+                for (key, value) in split_vorbis_comments(&data, &options)?.comments {
+                    let (key, value) = decode_comment(key, value, &options)?;
+                    f(&key, &value);
+                }
+                Ok(true)
+            }),
+            None => Ok(false),
+        }
+    }
+
+    /// Parse all PICTURE blocks, which hold embedded images like cover art
+    pub fn pictures(&mut self) -> Result<Vec<Picture>> {
+        let options = self.options;
+        let mut pictures = Vec::new();
+        while let Some(header) = self.search_block(6)? {
+            pictures.push(parse_body(self.file.by_ref(), &header, |body| parse_picture(body, header.length, &options))?);
+        }
+        Ok(pictures)
+    }
+
+    /// List the type, position and length of all remaining metadata blocks
+    ///
+    /// The bodies of the blocks are skipped without parsing them. The offsets are those of the
+    /// block headers, counted from the start of the reader.
+    pub fn block_layout(&mut self) -> Result<Vec<BlockEntry>> {
+        let mut layout = Vec::new();
+        let mut offset = self.state.offset;
+        while let Some(header) = self.next_header()? {
+            self.skip_body(&header)?;
+            layout.push(BlockEntry {
+                block_type: header.block_type,
+                offset,
+                length: header.length,
+                is_last: header.is_last,
+            });
+            offset = self.state.offset;
+        }
+        Ok(layout)
+    }
+
+    /// Get an overview of the remaining metadata blocks in a single pass
+    ///
+    /// The bodies of the blocks are skipped without parsing them.
+    pub fn summary(&mut self) -> Result<FlacSummary> {
+        let mut summary = FlacSummary::default();
+        while let Some(header) = self.next_header()? {
+            self.skip_body(&header)?;
+            summary.block_count += 1;
+            match header.block_type {
+                4 => summary.has_comments = true,
+                5 => summary.has_cuesheet = true,
+                6 => summary.has_pictures = true,
+                _ => (),
+            }
+        }
+        summary.audio_offset = self.state.offset;
+        Ok(summary)
+    }
+
+    /// Get the size of all metadata including the `fLaC` marker, which is the offset of the
+    /// first audio frame
+    ///
+    /// Metadata blocks that have not been read yet are skipped without parsing them.
+    pub fn metadata_size(&mut self) -> Result<u64> {
+        while let Some(header) = self.next_header()? {
+            self.skip_body(&header)?;
+        }
+        Ok(self.state.offset)
+    }
+
+    /// Get the total size of the PADDING blocks, excluding their headers
+    ///
+    /// Only the remaining metadata blocks are counted, they are skipped without parsing them.
+    pub fn padding_bytes(&mut self) -> Result<u32> {
+        let mut padding: u32 = 0;
+        while let Some(header) = self.next_header()? {
+            self.skip_body(&header)?;
+            if header.block_type == 1 {
+                padding = padding.saturating_add(header.length);
+            }
+        }
+        Ok(padding)
+    }
+
+    /// Get the reader positioned right after the metadata, at the start of the first audio frame
+    ///
+    /// Metadata blocks that have not been read yet are skipped.
+    pub fn into_inner(mut self) -> Result<R> {
+        self.metadata_size()?;
+        Ok(self.file)
+    }
+
+    /// Check the remaining metadata blocks against the structure required by the specification
+    ///
+    /// Unlike the `strict` option, this does not stop at the first problem, all violations are
+    /// collected. This should be called before any blocks are read, otherwise the order of the
+    /// blocks can't be checked. Only the STREAMINFO block is parsed, the other blocks are skipped.
+    /// Afterwards the reader is positioned at the first audio frame, unless the file is too
+    /// malformed to get there.
+    pub fn validate(&mut self) -> ::std::result::Result<(), Vec<FlacError>> {
+        let mut errors = Vec::new();
+        let mut stream_infos = 0;
+        let at_start = self.state.blocks_read == 0;
+        // The order is validated here, so reading the headers must not stop at the first problem,
+        // and a missing last-block flag is a problem even if the parser tolerates it
+        let options = self.options.strict(false).tolerant(false);
+        loop {
+            let first = self.state.blocks_read == 0;
+            let header = match self.state.read_header(self.file.by_ref(), &options) {
+                Ok(Some(header)) => header,
+                Ok(None) => break,
+                Err(FlacError::Io(ref e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    errors.push(FlacError::InvalidStructure("the file ends before a metadata block with the last-block flag"));
+                    break;
+                },
+                Err(e) => {
+                    errors.push(e);
+                    break;
+                },
+            };
+            let result = match header.block_type {
+                0 => {
+                    // A STREAMINFO block that is not first is either a duplicate or reported for
+                    // the first block
+                    stream_infos += 1;
+                    if header.length == 34 {
+                        parse_body(self.file.by_ref(), &header, parse_stream_info)
+                            .map(|stream_info| errors.extend(validate_stream_info(&stream_info)))
+                    } else {
+                        errors.push(FlacError::InvalidStructure("STREAMINFO must be 34 bytes long"));
+                        self.skip_body(&header)
+                    }
+                },
+                block_type => {
+                    if first {
+                        errors.push(FlacError::InvalidStructure("STREAMINFO must be the first metadata block"));
+                    }
+                    if block_type == 2 && header.length < 4 {
+                        errors.push(FlacError::InvalidStructure("an APPLICATION block must be at least 4 bytes long"));
+                    }
+                    if block_type == 3 && header.length % 18 != 0 {
+                        errors.push(FlacError::InvalidStructure("the length of a SEEKTABLE block must be a multiple of 18"));
+                    }
+                    if block_type == 127 {
+                        errors.push(FlacError::InvalidStructure("block type 127 is invalid"));
+                    }
+                    self.skip_body(&header)
+                },
+            };
+            // The position in the file is unknown after a failed block, so don't try to continue
+            if let Err(e) = result {
+                errors.push(e);
+                break;
+            }
+        }
+
+        if at_start && stream_infos == 0 {
+            errors.push(FlacError::NoStreamInfo);
+        }
+        if stream_infos > 1 {
+            errors.push(FlacError::InvalidStructure("STREAMINFO must occur only once"));
+        }
+        if errors.is_empty() {
+            // A last-block flag that is set too early or too late leaves the reader at a position
+            // that is not the start of an audio frame, a file without audio is fine
+            match self.file.fill_buf() {
+                Ok(buf) if buf.len() >= 2 && !(buf[0] == 0xFF && buf[1] & 0xFE == 0xF8) => {
+                    errors.push(FlacError::InvalidStructure("the last-block flag is not followed by an audio frame"));
+                },
+                Ok(_) => (),
+                Err(e) => errors.push(e.into()),
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Skips metadata blocks until a block of the given type is found
+    ///
+    /// Returns the header of the block with the reader positioned at the start of its body, or
+    /// `None` once the last metadata block has been passed without finding it.
+    fn search_block(&mut self, wanted: u8) -> Result<Option<BlockHeader>> {
+        while let Some(header) = self.next_header()? {
+            if header.block_type == wanted {
+                return Ok(Some(header));
+            }
+            self.skip_body(&header)?;
+        }
+        Ok(None)
+    }
+
+    /// Skips the body of a metadata block, if the reader is positioned at the start of it
+    fn skip_body(&mut self, header: &BlockHeader) -> Result<()> {
+        // Seeking past the end of the file succeeds, so a seekable reader is checked against its
+        // length first, the offset in the state is already past the body
+        if self.length.is_some_and(|length| self.state.offset > length) {
+            return Err(malformed_block(header));
+        }
+        (self.skip)(&mut self.file, header)
+    }
+
+    /// Reads the header of the next metadata block, or `None` after the last metadata block
+    fn next_header(&mut self) -> Result<Option<BlockHeader>> {
+        self.state.read_header(self.file.by_ref(), &self.options)
+    }
+}
+
+impl<R> FlacParser<R>
+where R: Read + BufRead + Seek {
+    /// Create a parser that skips the blocks it doesn't need by seeking past them
+    ///
+    /// This is much faster than reading through large blocks like pictures, but needs a reader
+    /// that can seek, like a file.
+    pub fn seekable(file: R) -> Result<FlacParser<R>> {
+        FlacParser::seekable_with_options(file, ParseOptions::default())
+    }
+
+    /// Create a parser with the given options that skips blocks by seeking past them
+    pub fn seekable_with_options(mut file: R, options: ParseOptions) -> Result<FlacParser<R>> {
+        // Every seek discards the buffer of a `BufReader`, so the length is only looked up once
+        let start = file.stream_position()?;
+        let end = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(start))?;
+        let mut parser = FlacParser::with_options(file, options)?;
+        parser.skip = seek_body;
+        parser.length = Some(end.saturating_sub(start));
+        Ok(parser)
+    }
+
+    /// Find the next block of the given type, skipping the bodies of the other blocks
+    ///
+    /// Returns the offset of the block header and the length of the body. The body of the found
+    /// block is skipped as well, so the block can be read by seeking the underlying reader to it.
+    pub fn find_block(&mut self, block_type: u8) -> Result<Option<(u64, u32)>> {
+        let mut offset = self.state.offset;
+        while let Some(header) = self.next_header()? {
+            self.skip_body(&header)?;
+            if header.block_type == block_type {
+                return Ok(Some((offset, header.length)));
+            }
+            offset = self.state.offset;
+        }
+        Ok(None)
+    }
+}
+
+/// Keeps track of the metadata blocks that have been read
+pub struct ReadState {
+    /// True once the last metadata block has been read
+    pub done: bool,
+    /// The offset in the file of the header of the next metadata block
+    pub offset: u64,
+    blocks_read: u32,
+    seen_comments: bool,
+}
+
+impl ReadState {
+    pub fn new(offset: u64) -> ReadState {
+        ReadState { done: false, offset, blocks_read: 0, seen_comments: false }
+    }
+
+    /// Reads the header of the next metadata block, with the `strict` option the order of the
+    /// blocks is validated
+    ///
+    /// Returns `None` after the last metadata block. With the `tolerant` option, that includes a
+    /// file that ends where the next header should be, as some files never set the last-block
+    /// flag.
+    pub fn read_header<R>(&mut self, file: &mut R, options: &ParseOptions) -> Result<Option<BlockHeader>>
+    where R: Read {
+        if self.done {
+            return Ok(None);
+        }
+        let header = if options.tolerant {
+            let mut buf = [0; 4];
+            let read = loop {
+                match file.read(&mut buf[..1]) {
+                    Err(ref e) if e.kind() == ErrorKind::Interrupted => (),
+                    result => break result?,
+                }
+            };
+            if read == 0 {
+                self.done = true;
+                return Ok(None);
+            }
+            file.read_exact(&mut buf[1..])?;
+            decode_block_header(&buf)
+        } else {
+            read_block_header(file)?
+        };
+        if options.strict {
+            if (self.blocks_read == 0) != (header.block_type == 0) {
+                return Err(FlacError::InvalidStructure("STREAMINFO must be the first metadata block and occur only once"));
+            }
+            if header.block_type == 4 && self.seen_comments {
+                return Err(FlacError::InvalidStructure("there must be at most one VORBIS_COMMENT block"));
+            }
+        }
+        self.done = header.is_last;
+        self.offset += 4 + u64::from(header.length);
+        self.blocks_read += 1;
+        self.seen_comments |= header.block_type == 4;
+        Ok(Some(header))
+    }
+}
+
+impl<R> FlacParser<io::BufReader<R>>
+where R: Read {
+    /// Create a parser for a reader that does not implement `BufRead`, by buffering it internally
+    pub fn from_read(file: R) -> Result<FlacParser<io::BufReader<R>>> {
+        FlacParser::new(io::BufReader::new(file))
+    }
+}
+
+impl<'a, R> MusicDataParser<'a, VorbisMetadata> for FlacParser<R>
+where R: Read + BufRead {
+    fn parse(&mut self) -> Result<VorbisMetadata> {
+        self.comments()?.ok_or(FlacError::NoCommentBlock)
+    }
+}
+
+pub fn parse<R>(file: &mut R) -> Result<MusicMetaData>
+where R: Read + BufRead {
+    open_flac(file, ParseOptions::default())?.parse().map(|x| x.into())
+}
+
+/// Creates a parser like `FlacParser::with_options`, but reports every file without the `fLaC`
+/// marker as `FlacError::NotFlac`
+fn open_flac<R>(file: R, options: ParseOptions) -> Result<FlacParser<R>>
+where R: Read + BufRead {
+    FlacParser::with_options(file, options).map_err(not_flac)
+}
+
+/// Maps the errors of creating a parser to `FlacError::NotFlac`, except the ones that say more
+fn not_flac(error: FlacError) -> FlacError {
+    match error {
+        // This is still a FLAC stream, so a more specific error is more helpful
+        FlacError::OggEncapsulated => FlacError::OggEncapsulated,
+        // A file that is too short for the marker is not a FLAC file, but other I/O errors are
+        // problems of the reader
+        FlacError::Io(e) if e.kind() != ErrorKind::UnexpectedEof => FlacError::Io(e),
+        _ => FlacError::NotFlac,
+    }
+}
+
+/// Parses the comments of many FLAC files after each other, e.g. to scan a music library
+///
+/// Unlike `parse`, the buffer for the comment block is reused between files and the map passed to
+/// `parse_into` keeps its capacity, which saves allocations when parsing thousands of files. The
+/// field names and values are still allocated for every file.
+#[derive(Debug, Default)]
+pub struct BulkParser {
+    options: ParseOptions,
+    /// The body of the last comment block that was read
+    buffer: Vec<u8>,
+}
+
+impl BulkParser {
+    /// Create a parser with the default options
+    pub fn new() -> BulkParser {
+        BulkParser::default()
+    }
+
+    /// Create a parser with the given options
+    pub fn with_options(options: ParseOptions) -> BulkParser {
+        BulkParser { options, buffer: Vec::new() }
+    }
+
+    /// Parses the comments of a FLAC file into `out`, which is cleared first
+    ///
+    /// The result is the same as that of `parse`, so a file without a VORBIS_COMMENT block results
+    /// in `FlacError::NoCommentBlock`. On errors `out` may hold some of the fields.
+    pub fn parse_into<R>(&mut self, file: &mut R, out: &mut MusicMetaData) -> Result<()>
+    where R: Read + BufRead {
+        out.map.clear();
+        let options = self.options;
+        let buffer = &mut self.buffer;
+        let mut parser = open_flac(file, options)?;
+        let header = parser.search_block(4)?.ok_or(FlacError::NoCommentBlock)?;
+        parse_body(parser.file.by_ref(), &header, |body| {
+            read_comment_block_into(body, &options, buffer)?;
+            let raw = split_vorbis_comments(buffer, &options)?;
+            // The vendor string isn't kept, but invalid UTF-8 in it is an error like in `parse`
+            if !options.lossy_utf8 && str::from_utf8(raw.vendor).is_err() {
+                return Err(FlacError::InvalidUtf8 { key: None });
+            }
+            for (key, value) in raw.comments {
+                let (key, value) = decode_comment(key, value, &options)?;
+                // Like `MusicData::map`, only the first value of a repeated field is kept
+                out.map.entry(key).or_insert(value);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Parses the metadata of a FLAC file that is already in memory, e.g. after downloading it
+///
+/// This runs the same parser as `parse`, without wrapping the data in a `Cursor`.
+pub fn parse_slice(mut data: &[u8]) -> Result<MusicMetaData> {
+    parse(&mut data)
+}
+
+/// Parses the metadata of a native FLAC file or an Ogg file, depending on the first 4 bytes
+///
+/// Ogg files are parsed with `OggParser`, so Ogg Vorbis and Ogg Opus files are supported as well.
+/// Other files result in `FlacError::Unsupported`, and files shorter than the magic in
+/// `FlacError::NotFlac`.
+pub fn parse_any<R>(file: &mut R) -> Result<MusicMetaData>
+where R: Read + BufRead {
+    let mut magic = [0; 4];
+    match file.read_exact(&mut magic) {
+        Ok(()) => (),
+        Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => return Err(FlacError::NotFlac),
+        Err(e) => return Err(e.into()),
+    }
+    // Put the magic back in front of the reader, so the parsers can check it themselves
+    let mut file = (&magic[..]).chain(file);
+    match &magic {
+        b"fLaC" => parse(&mut file),
+        b"OggS" => OggParser::new(file)?.parse().map(|x| x.into()),
+        _ => Err(FlacError::Unsupported("a container other than FLAC and Ogg")),
+    }
+}
+
+/// Opens the file at the given path and parses its metadata
+///
+/// Failing to open the file results in `FlacError::Io`, while a file that is not a FLAC file
+/// results in `FlacError::NotFlac`
+pub fn parse_path<P>(path: P) -> Result<MusicMetaData>
+where P: AsRef<Path> {
+    let file = File::open(path)?;
+    FlacParser::seekable(io::BufReader::new(file)).map_err(not_flac)?.parse().map(|x| x.into())
+}
+
+/// Opens the file at the given path and parses its metadata, regardless of its extension
+///
+/// The container is detected from the contents like `parse_any` does, the result records whether
+/// it matches the extension. Contents other than FLAC and Ogg are not an error, they result in
+/// `None` metadata instead, like a file without a comment block.
+pub fn parse_path_checked<P>(path: P) -> Result<ParsedFile>
+where P: AsRef<Path> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|x| x.to_str()).map(|x| x.to_ascii_lowercase());
+    let mut file = io::BufReader::new(File::open(path)?);
+    let (is_flac, is_ogg) = {
+        let start = file.fill_buf()?;
+        (start.starts_with(b"fLaC"), start.starts_with(b"OggS"))
+    };
+    let extension_matches = match extension.as_deref() {
+        Some("flac") | Some("fla") => is_flac,
+        Some("ogg") | Some("oga") | Some("opus") => is_ogg,
+        // Other extensions, like `.mp3`, only describe contents that are neither FLAC nor Ogg
+        _ => !is_flac && !is_ogg,
+    };
+    let metadata = if is_flac || is_ogg {
+        match parse_any(&mut file) {
+            Ok(metadata) => Some(metadata),
+            Err(FlacError::NoCommentBlock) => None,
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+    Ok(ParsedFile::new(metadata, extension_matches))
+}
+
+/// Opens the file at the given path and extracts the MIME type and data of its front cover
+///
+/// If there is no picture with the front cover type, the first picture is used instead. Blocks
+/// other than PICTURE blocks are skipped without parsing them.
+pub fn extract_front_cover<P>(path: P) -> Result<Option<(String, Vec<u8>)>>
+where P: AsRef<Path> {
+    let mut parser = FlacParser::seekable(io::BufReader::new(File::open(path)?))?;
+    let options = parser.options;
+    let mut first = None;
+    while let Some(header) = parser.search_block(6)? {
+        let picture = parse_body(parser.file.by_ref(), &header, |body| parse_picture(body, header.length, &options))?;
+        if picture.picture_type == PictureType::FrontCover {
+            return Ok(Some((picture.mime_type, picture.data)));
+        }
+        first = first.or(Some(picture));
+    }
+    Ok(first.map(|picture| (picture.mime_type, picture.data)))
+}
+
+/// Returns true if the reader is a FLAC file
+pub fn is_flac_file<R>(file: &mut R) -> Result<bool>
+where R: Read {
+    let mut buffer = [0; 4];
+    file.read_exact(&mut buffer)?;
+    Ok(buffer == "fLaC".as_bytes())
+}
+
+/// Reads the `fLaC` marker, optionally skipping an ID3v2 tag that precedes it
+///
+/// Returns the offset of the marker, or `None` if the reader is not a FLAC file
+fn find_flac_marker<R>(file: &mut R, skip_id3: bool) -> Result<Option<u64>>
+where R: Read {
+    let mut buffer = [0; 4];
+    file.read_exact(&mut buffer)?;
+    let mut offset = 0;
+    if skip_id3 && &buffer[..3] == b"ID3" {
+        // The rest of the 10 byte header: minor version, flags and a synchsafe size
+        let mut header = [0; 6];
+        file.read_exact(&mut header)?;
+        let size = header[2..].iter().fold(0, |size, &b| size << 7 | u64::from(b & 0x7F));
+        // The size excludes the header and the optional footer
+        let footer = if header[1] & 0x10 != 0 { 10 } else { 0 };
+        skip_n(file.by_ref(), size + footer)?;
+        offset = 10 + size + footer;
+        file.read_exact(&mut buffer)?;
+    }
+    if buffer == "OggS".as_bytes() {
+        return Err(FlacError::OggEncapsulated);
+    }
+    Ok(if buffer == "fLaC".as_bytes() { Some(offset) } else { None })
+}
+
+/// Reads a metadata block header if the reader is positioned at the start of a block
+pub fn read_block_header<R>(file: &mut R) -> Result<BlockHeader>
+where R: Read {
+    let mut block_header_buf = [0; 4];
+    file.read_exact(&mut block_header_buf)?;
+    Ok(decode_block_header(&block_header_buf))
+}
+
+/// Parses the body of a metadata block with the given parser
+///
+/// Exactly the length from the header is consumed, even if the parser reads less, so the reader
+/// is left at the start of the next block. If the file ends before the end of the block, this
+/// results in `FlacError::MalformedBlock`.
+pub fn parse_body<R, T, F>(file: R, header: &BlockHeader, parse: F) -> Result<T>
+where R: Read, F: FnOnce(&mut io::Take<R>) -> Result<T> {
+    let mut body = file.take(u64::from(header.length));
+    // A parser that runs into the end of a short block fails, but the block itself is to blame
+    let value = parse(&mut body);
+    io::copy(&mut body, &mut io::sink())?;
+    if body.limit() != 0 {
+        return Err(malformed_block(header));
+    }
+    value
+}
+
+/// Skips the body of a metadata block, if the reader is positioned at the start of it
+pub fn skip_body<R>(file: R, header: &BlockHeader) -> Result<()>
+where R: Read {
+    let skipped = io::copy(&mut file.take(u64::from(header.length)), &mut io::sink())?;
+    if skipped != u64::from(header.length) {
+        return Err(malformed_block(header));
+    }
+    Ok(())
+}
+
+/// Checks the values of a STREAMINFO block against the ranges allowed by the specification
+fn validate_stream_info(stream_info: &StreamInfo) -> Vec<FlacError> {
+    let mut errors = Vec::new();
+    if stream_info.min_block_size < 16 {
+        errors.push(FlacError::InvalidStructure("the minimum block size must be at least 16 samples"));
+    }
+    if stream_info.max_block_size < stream_info.min_block_size {
+        errors.push(FlacError::InvalidStructure("the maximum block size is smaller than the minimum block size"));
+    }
+    if stream_info.sample_rate == 0 || stream_info.sample_rate > 655_350 {
+        errors.push(FlacError::InvalidStructure("the sample rate must be between 1 and 655350 Hz"));
+    }
+    errors
+}
+
+/// Skips the body of a metadata block by seeking past it, if the reader is positioned at the start
+/// of it
+///
+/// Seeking past the end of the file succeeds, so the caller has to check the length of the block
+/// against the length of the file.
+fn seek_body<R>(file: &mut R, header: &BlockHeader) -> Result<()>
+where R: BufRead + Seek {
+    // A body that is already buffered is consumed instead, as seeking discards the buffer
+    let buffered = file.fill_buf()?.len();
+    if buffered as u64 >= u64::from(header.length) {
+        file.consume(header.length as usize);
+    } else {
+        file.seek(SeekFrom::Current(i64::from(header.length)))?;
+    }
+    Ok(())
+}
+
+/// The error for a block that claims to be longer than the rest of the file
+pub fn malformed_block(header: &BlockHeader) -> FlacError {
+    FlacError::MalformedBlock { block_type: header.block_type, declared_size: header.length }
+}
+
+/// Parses vorbis comments if the reader is positioned at the start of the comment block
+///
+/// The reader has to end at the end of the comment block, e.g. by limiting it with `Read::take`
+pub fn parse_vorbis_comments<R>(file: &mut R, options: &ParseOptions) -> Result<VorbisMetadata> 
+where R: Read {
+    let data = read_comment_block(file, options)?;
+    decode_vorbis_comments(&data, options)
+}
+
+/// Reads the rest of a comment block, which has to be at most `max_comment_size` bytes
+fn read_comment_block<R>(file: &mut R, options: &ParseOptions) -> Result<Vec<u8>>
+where R: Read {
+    let mut data = Vec::new();
+    read_comment_block_into(file, options, &mut data)?;
+    Ok(data)
+}
+
+/// Reads the rest of a comment block into a buffer, replacing its previous contents
+fn read_comment_block_into<R>(file: &mut R, options: &ParseOptions, data: &mut Vec<u8>) -> Result<()>
+where R: Read {
+    data.clear();
+    // Read one byte more than allowed, to find out if the block is too large without reading it all
+    file.by_ref().take((options.max_comment_size as u64).saturating_add(1)).read_to_end(data)?;
+    if data.len() > options.max_comment_size {
+        return Err(FlacError::CommentTooLarge);
+    }
+    Ok(())
+}
+
+/// Read n bytes from the reader and construct it into a string
+///
+/// The bytes are read in full before decoding, so invalid UTF-8 leaves the reader positioned
+/// after the string and results in `FlacError::InvalidUtf8`, unless the `lossy_utf8` option is set
+pub fn read_n<R>(reader: R, bytes_to_read: u64, options: &ParseOptions) -> Result<String>
+where R: Read {
+    let mut buf = Vec::new();
+    let mut chunk = reader.take(bytes_to_read);
+    let n = chunk.read_to_end(&mut buf)?;
+    if n as u64 != bytes_to_read {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "string is shorter than its length claims").into());
+    }
+    decode_string(buf, options)
+}
+
+/// Discard n bytes from the reader
+///
+/// Unlike `BufRead::consume` this also reads past the data that is currently buffered
+pub fn skip_n<R>(reader: R, bytes_to_skip: u64) -> Result<()>
+where R: Read {
+    let skipped = io::copy(&mut reader.take(bytes_to_skip), &mut io::sink())?;
+    if skipped != bytes_to_skip {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "metadata block is shorter than its header claims").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+    use std::path::PathBuf;
+    use crate::MusicData;
+    use crate::test_util::{block, comments, flac, ogg_packets, picture, stream_info};
+
+    #[test]
+    fn comment_block_can_be_the_last_block() {
+        let file = flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "Get Got")]))]);
+        assert_eq!(parse_slice(&file).unwrap().title(), Some("Get Got"));
+    }
+
+    #[test]
+    fn last_block_without_comments_is_no_comment_block() {
+        let file = flac(&[block(0, true, &stream_info()), block(4, false, &comments(&[("TITLE", "after the end")]))]);
+        match parse_slice(&file) {
+            Err(FlacError::NoCommentBlock) => (),
+            other => panic!("expected NoCommentBlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn block_type_64_is_not_a_comment_block() {
+        // 68 would alias to VORBIS_COMMENT with a six bit mask
+        let file = flac(&[block(0, false, &stream_info()), block(68, false, b"junk"), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let mut parser = FlacParser::new(&file[..]).unwrap();
+        let layout = parser.block_layout().unwrap();
+        let types: Vec<u8> = layout.iter().map(|entry| entry.block_type).collect();
+        assert_eq!(types, [0, 68, 4]);
+        assert_eq!(parse_slice(&file).unwrap().title(), Some("x"));
+    }
+
+    #[test]
+    fn padding_larger_than_the_buffer_is_skipped() {
+        let padding = vec![0; 100_000];
+        let file = flac(&[block(0, false, &stream_info()), block(1, false, &padding), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let mut reader = io::BufReader::with_capacity(16, &file[..]);
+        assert_eq!(parse(&mut reader).unwrap().title(), Some("x"));
+    }
+
+    #[test]
+    fn string_longer_than_the_data_is_an_error() {
+        match read_n(&b"abc"[..], 10, &ParseOptions::new()) {
+            Err(FlacError::Io(ref e)) if e.kind() == ErrorKind::UnexpectedEof => (),
+            other => panic!("expected UnexpectedEof, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn comment_block_longer_than_the_file_is_an_error() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let length = file.len();
+        file.truncate(length - 3);
+        match parse_slice(&file) {
+            Err(FlacError::MalformedBlock { block_type: 4, .. }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn comment_longer_than_the_block_is_an_error() {
+        let mut body = comments(&[("TITLE", "x")]);
+        // The length of the only comment, right after the vendor string and the comment count
+        body[12] = 0xFF;
+        let file = flac(&[block(0, false, &stream_info()), block(4, true, &body)]);
+        match parse_slice(&file) {
+            Err(FlacError::Truncated) => (),
+            other => panic!("expected Truncated, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tolerant_parser_accepts_a_missing_last_block_flag() {
+        let file = flac(&[block(0, false, &stream_info()), block(1, false, &[0; 10])]);
+        assert_eq!(FlacParser::tolerant(&file[..]).unwrap().padding_bytes().unwrap(), 10);
+        let options = ParseOptions::new().tolerant(true);
+        assert_eq!(FlacParser::with_options(&file[..], options).unwrap().padding_bytes().unwrap(), 10);
+        match FlacParser::new(&file[..]).unwrap().padding_bytes() {
+            Err(FlacError::Io(ref e)) if e.kind() == ErrorKind::UnexpectedEof => (),
+            other => panic!("expected UnexpectedEof, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn find_block_seeks_past_a_large_picture() {
+        let picture = vec![0; 1 << 20];
+        let file = flac(&[block(0, false, &stream_info()), block(6, false, &picture), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let mut parser = FlacParser::seekable(io::Cursor::new(&file[..])).unwrap();
+        assert_eq!(parser.find_block(6).unwrap(), Some((42, 1 << 20)));
+        let comments_offset = 42 + 4 + (1 << 20);
+        assert_eq!(parser.find_block(4).unwrap().map(|(offset, _)| offset), Some(comments_offset));
+        assert_eq!(parser.find_block(6).unwrap(), None);
+    }
+
+    #[test]
+    fn find_block_reports_a_truncated_block() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(6, false, &[0; 100]), block(4, true, &comments(&[("TITLE", "x")]))]);
+        file.truncate(42 + 4 + 50);
+        let mut parser = FlacParser::seekable(io::Cursor::new(&file[..])).unwrap();
+        match parser.find_block(4) {
+            Err(FlacError::MalformedBlock { block_type: 6, declared_size: 100 }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+
+    /// A reader that can't seek, like a pipe
+    struct Pipe<'a>(&'a [u8]);
+
+    impl<'a> Read for Pipe<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    /// A seekable reader that counts how often it is seeked
+    struct CountSeeks<'a> {
+        inner: io::Cursor<&'a [u8]>,
+        seeks: usize,
+    }
+
+    impl<'a> Read for CountSeeks<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl<'a> Seek for CountSeeks<'a> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seeks += 1;
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn seeking_and_reading_give_the_same_layout() {
+        let file = flac(&[block(0, false, &stream_info()), block(6, false, &[0; 20_000]), block(1, false, &[0; 10]), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let seeked = FlacParser::seekable(io::Cursor::new(&file[..])).unwrap().block_layout().unwrap();
+        let read = FlacParser::from_read(Pipe(&file)).unwrap().block_layout().unwrap();
+        assert_eq!(seeked, read);
+        assert_eq!(seeked.len(), 4);
+    }
+
+    #[test]
+    fn seeking_and_reading_report_the_same_truncated_block() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(6, true, &[0; 20_000])]);
+        file.truncate(42 + 4 + 100);
+        let errors = [
+            FlacParser::seekable(io::Cursor::new(&file[..])).unwrap().metadata_size(),
+            FlacParser::from_read(Pipe(&file)).unwrap().metadata_size(),
+        ];
+        for error in errors.iter() {
+            match *error {
+                Err(FlacError::MalformedBlock { block_type: 6, declared_size: 20_000 }) => (),
+                ref other => panic!("expected MalformedBlock, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn buffered_blocks_are_skipped_without_seeking() {
+        let file = flac(&[block(0, false, &stream_info()), block(1, false, &[0; 10]), block(2, false, b"riffdata"), block(4, true, &comments(&[("TITLE", "x")]))]);
+        let reader = io::BufReader::new(CountSeeks { inner: io::Cursor::new(&file[..]), seeks: 0 });
+        let mut parser = FlacParser::seekable(reader).unwrap();
+        let seeks = parser.file.get_ref().seeks;
+        assert_eq!(parser.metadata_size().unwrap(), file.len() as u64);
+        assert_eq!(parser.file.get_ref().seeks, seeks);
+    }
+
+    /// Writes the data to a file with the given name in the temporary directory
+    fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+        let path = env::temp_dir().join(format!("flacparse-{}-{}", process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn non_flac_contents_with_flac_extension() {
+        let path = temp_file("mislabeled.flac", b"ID3\x04\x00\x00\x00\x00\x00\x00mp3 frames");
+        let parsed = parse_path_checked(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!parsed.extension_matches_content());
+        assert!(parsed.metadata.is_none());
+    }
+
+    #[test]
+    fn other_extension_with_other_contents_matches() {
+        let path = temp_file("song.mp3", b"ID3\x04\x00\x00\x00\x00\x00\x00mp3 frames");
+        let parsed = parse_path_checked(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(parsed.extension_matches_content());
+    }
+
+    #[test]
+    fn untagged_flac_is_not_an_error() {
+        let path = temp_file("untagged.FLAC", &flac(&[block(0, true, &stream_info())]));
+        let parsed = parse_path_checked(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(parsed.extension_matches_content());
+        assert!(parsed.metadata.is_none());
+    }
+
+    #[test]
+    fn flac_contents_with_ogg_extension() {
+        let path = temp_file("mislabeled.ogg", &flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "x")]))]));
+        let parsed = parse_path_checked(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(!parsed.extension_matches_content());
+        assert_eq!(parsed.metadata.unwrap().title(), Some("x"));
+    }
+
+    #[test]
+    fn inputs_too_short_for_a_header() {
+        let inputs: [&[u8]; 4] = [b"", b"fLa", b"fLaC", b"fLaC\x84\x00"];
+        for input in inputs.iter() {
+            assert!(parse_slice(input).is_err());
+        }
+        assert!(matches!(parse_slice(b"fLa"), Err(FlacError::NotFlac)));
+    }
+
+    #[test]
+    fn id3_size_past_the_end() {
+        let mut file = b"ID3\x04\x00\x00\x7F\x7F\x7F\x7F".to_vec();
+        file.extend(flac(&[block(0, true, &stream_info())]));
+        assert!(FlacParser::tolerant(&file[..]).is_err());
+    }
+
+    #[test]
+    fn verify_md5_of_an_unknown_signature() {
+        let file = flac(&[block(0, true, &stream_info())]);
+        assert_eq!(FlacParser::new(&file[..]).unwrap().verify_md5().unwrap(), Md5Status::Unknown);
+        let mut signed = stream_info();
+        signed[33] = 0xAB;
+        let file = flac(&[block(0, true, &signed)]);
+        assert_eq!(FlacParser::new(&file[..]).unwrap().verify_md5().unwrap(), Md5Status::Present);
+    }
+
+
+    #[test]
+    fn parse_any_dispatches_on_the_magic() {
+        let file = flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "Native")]))]);
+        assert_eq!(parse_any(&mut &file[..]).unwrap().get("TITLE"), Some("Native"));
+
+        let mut packet = b"\x03vorbis".to_vec();
+        packet.extend(comments(&[("TITLE", "Ogg")]));
+        packet.push(1);
+        let file = ogg_packets(&[b"\x01vorbis\x00\x00\x00\x00", &packet]);
+        assert_eq!(parse_any(&mut &file[..]).unwrap().get("TITLE"), Some("Ogg"));
+
+        match parse_any(&mut &b"RIFF\0\0\0\0WAVE"[..]) {
+            Err(FlacError::Unsupported(_)) => (),
+            other => panic!("expected Unsupported, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_any_on_short_input() {
+        for data in &[&b""[..], b"f", b"fLa", b"Og"] {
+            match parse_any(&mut &data[..]) {
+                Err(FlacError::NotFlac) => (),
+                other => panic!("expected NotFlac, got {:?}", other),
+            }
+        }
+    }
+
+
+    #[test]
+    fn extract_jpeg_front_cover() {
+        let jpeg = b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00\xFF\xD9";
+        let path = temp_file("cover.flac", &flac(&[
+            block(0, false, &stream_info()),
+            block(1, false, &[0; 8192]),
+            block(6, false, &picture(4, "image/png", b"\x89PNG")),
+            block(6, true, &picture(3, "image/jpeg", jpeg)),
+        ]));
+        let cover = extract_front_cover(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cover, Some(("image/jpeg".to_string(), jpeg.to_vec())));
+    }
+
+    #[test]
+    fn parse_path_skips_blocks_by_seeking() {
+        let path = temp_file("seek.flac", &flac(&[
+            block(0, false, &stream_info()),
+            block(6, false, &picture(3, "image/jpeg", &[0; 100_000])),
+            block(4, true, &comments(&[("TITLE", "Seek")])),
+        ]));
+        let metadata = parse_path(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(metadata.get("TITLE"), Some("Seek"));
+
+        let path = temp_file("seek.txt", b"not a flac file");
+        let result = parse_path(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(FlacError::NotFlac) => (),
+            other => panic!("expected NotFlac, got {:?}", other),
+        }
+    }
+
+
+    fn validate(file: &[u8]) -> ::std::result::Result<(), Vec<FlacError>> {
+        FlacParser::new(file).unwrap().validate()
+    }
+
+    fn has_structure_error(errors: &[FlacError], message: &str) -> bool {
+        errors.iter().any(|e| matches!(e, FlacError::InvalidStructure(m) if *m == message))
+    }
+
+    #[test]
+    fn validate_a_valid_file() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(4, false, &comments(&[("TITLE", "x")])), block(1, true, &[0; 16])]);
+        assert!(validate(&file).is_ok());
+        // The first audio frame starts with a frame sync code
+        file.extend_from_slice(&[0xFF, 0xF8, 0x69, 0x08]);
+        let mut parser = FlacParser::new(&file[..]).unwrap();
+        assert!(parser.validate().is_ok());
+        assert_eq!(parser.into_inner().unwrap(), [0xFF, 0xF8, 0x69, 0x08]);
+    }
+
+    #[test]
+    fn validate_stream_info_length() {
+        let errors = validate(&flac(&[block(0, true, &stream_info()[..30])])).unwrap_err();
+        assert!(has_structure_error(&errors, "STREAMINFO must be 34 bytes long"), "{:?}", errors);
+    }
+
+    #[test]
+    fn validate_stream_info_not_first() {
+        let errors = validate(&flac(&[block(1, false, &[0; 4]), block(0, true, &stream_info())])).unwrap_err();
+        assert!(has_structure_error(&errors, "STREAMINFO must be the first metadata block"), "{:?}", errors);
+    }
+
+    #[test]
+    fn validate_duplicate_stream_info() {
+        let errors = validate(&flac(&[block(0, false, &stream_info()), block(0, true, &stream_info())])).unwrap_err();
+        assert!(has_structure_error(&errors, "STREAMINFO must occur only once"), "{:?}", errors);
+    }
+
+    #[test]
+    fn validate_block_past_the_end() {
+        let mut file = flac(&[block(0, false, &stream_info()), block(1, true, &[0; 64])]);
+        file.truncate(file.len() - 10);
+        for errors in &[validate(&file), FlacParser::seekable(io::Cursor::new(&file)).unwrap().validate()] {
+            let errors = errors.as_ref().unwrap_err();
+            assert!(matches!(errors[..], [FlacError::MalformedBlock { block_type: 1, declared_size: 64 }]), "{:?}", errors);
+        }
+    }
+
+
+    #[test]
+    fn reused_bulk_parser_matches_parse() {
+        let files = [
+            flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "One"), ("ARTIST", "a"), ("artist", "b")]))]),
+            flac(&[block(0, false, &stream_info()), block(1, false, &[0; 100]), block(4, true, &comments(&[("ALBUM", "Two")]))]),
+            flac(&[block(0, true, &stream_info())]),
+            flac(&[block(0, false, &stream_info()), block(4, true, &comments(&[("TITLE", "Three"), ("GENRE", "")]))]),
+        ];
+        let mut bulk = BulkParser::new();
+        let mut out = MusicMetaData::new();
+        for file in &files {
+            match (bulk.parse_into(&mut &file[..], &mut out), parse_slice(file)) {
+                (Ok(()), Ok(fresh)) => assert_eq!(out, fresh),
+                (Err(FlacError::NoCommentBlock), Err(FlacError::NoCommentBlock)) => (),
+                other => panic!("BulkParser and parse disagree: {:?}", other),
+            }
+        }
+        assert_eq!(out.get("TITLE"), Some("Three"));
+        assert!(!out.contains("ALBUM"));
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::{Error, ErrorKind};
+use std::io::prelude::*;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use crate::{FlacError, ParseOptions, Result};
+use crate::parser::{read_n, skip_n};
+
+/// Represents a PICTURE block, which holds embedded images like cover art
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Picture {
+    /// The picture type according to the ID3v2 APIC frame
+    pub picture_type: PictureType,
+    /// The MIME type of the picture, or `-->` if `data` holds a URL to the picture
+    pub mime_type: String,
+    /// The description of the picture, may be empty
+    pub description: String,
+    /// The width of the picture in pixels
+    pub width: u32,
+    /// The height of the picture in pixels
+    pub height: u32,
+    /// The color depth of the picture in bits per pixel
+    pub color_depth: u32,
+    /// The number of colors used for indexed-color pictures, 0 for other pictures
+    pub colors_used: u32,
+    /// The binary picture data
+    pub data: Vec<u8>,
+}
+
+impl Picture {
+    /// Find the front cover, if there is one
+    pub fn front_cover(pictures: &[Picture]) -> Option<&Picture> {
+        pictures.iter().find(|p| p.picture_type == PictureType::FrontCover)
+    }
+}
+
+/// The type of a picture, as defined by the ID3v2 APIC frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PictureType {
+    /// A picture that has none of the other types
+    Other = 0,
+    /// A 32x32 pixels PNG file icon
+    FileIcon = 1,
+    /// Another file icon
+    OtherFileIcon = 2,
+    /// The front cover
+    FrontCover = 3,
+    /// The back cover
+    BackCover = 4,
+    /// A page of the leaflet
+    LeafletPage = 5,
+    /// A picture of the media, e.g. the label side of a CD
+    Media = 6,
+    /// The lead artist or lead performer
+    LeadArtist = 7,
+    /// The artist or performer
+    Artist = 8,
+    /// The conductor
+    Conductor = 9,
+    /// The band or orchestra
+    Band = 10,
+    /// The composer
+    Composer = 11,
+    /// The lyricist or text writer
+    Lyricist = 12,
+    /// The recording location
+    RecordingLocation = 13,
+    /// A picture taken during the recording
+    DuringRecording = 14,
+    /// A picture taken during the performance
+    DuringPerformance = 15,
+    /// A screen capture of a movie or video
+    ScreenCapture = 16,
+    /// A bright colored fish
+    BrightColoredFish = 17,
+    /// An illustration
+    Illustration = 18,
+    /// The logo of the band or artist
+    BandLogo = 19,
+    /// The logo of the publisher or studio
+    PublisherLogo = 20,
+}
+
+impl From<u32> for PictureType {
+    /// Converts the number of a picture type, reserved numbers result in `PictureType::Other`
+    fn from(value: u32) -> PictureType {
+        match value {
+            1 => PictureType::FileIcon,
+            2 => PictureType::OtherFileIcon,
+            3 => PictureType::FrontCover,
+            4 => PictureType::BackCover,
+            5 => PictureType::LeafletPage,
+            6 => PictureType::Media,
+            7 => PictureType::LeadArtist,
+            8 => PictureType::Artist,
+            9 => PictureType::Conductor,
+            10 => PictureType::Band,
+            11 => PictureType::Composer,
+            12 => PictureType::Lyricist,
+            13 => PictureType::RecordingLocation,
+            14 => PictureType::DuringRecording,
+            15 => PictureType::DuringPerformance,
+            16 => PictureType::ScreenCapture,
+            17 => PictureType::BrightColoredFish,
+            18 => PictureType::Illustration,
+            19 => PictureType::BandLogo,
+            20 => PictureType::PublisherLogo,
+            _ => PictureType::Other,
+        }
+    }
+}
+
+impl From<PictureType> for u32 {
+    fn from(picture_type: PictureType) -> u32 {
+        picture_type as u32
+    }
+}
+
+/// Parses a PICTURE block of the given length if the reader is positioned at the start of the block
+pub fn parse_picture<R>(file: &mut R, length: u32, options: &ParseOptions) -> Result<Picture>
+where R: Read {
+    // Every field is checked against the rest of the block before reading it, so a corrupt length
+    // is reported as a malformed block instead of running into the next block
+    let mut remaining = u64::from(length);
+    let mut claim = |n: u64| {
+        if n > remaining {
+            return Err(FlacError::MalformedBlock { block_type: 6, declared_size: length });
+        }
+        remaining -= n;
+        Ok(())
+    };
+
+    claim(4)?;
+    let picture_type = file.read_u32::<BigEndian>()?;
+    // The picture types 0 to 20 are defined by the ID3v2 APIC frame, other values are reserved
+    if picture_type > 20 {
+        return Err(FlacError::InvalidPictureType(picture_type));
+    }
+
+    let mime_type = {
+        claim(4)?;
+        let length = file.read_u32::<BigEndian>()?;
+        claim(u64::from(length))?;
+        read_n(file.by_ref(), u64::from(length), options)?
+    };
+    let description = {
+        claim(4)?;
+        let length = file.read_u32::<BigEndian>()?;
+        claim(u64::from(length))?;
+        read_n(file.by_ref(), u64::from(length), options)?
+    };
+
+    // Width, height, color depth and the number of colors
+    claim(16)?;
+    let width = file.read_u32::<BigEndian>()?;
+    let height = file.read_u32::<BigEndian>()?;
+    let color_depth = file.read_u32::<BigEndian>()?;
+    let colors_used = file.read_u32::<BigEndian>()?;
+
+    let data = {
+        claim(4)?;
+        let length = file.read_u32::<BigEndian>()?;
+        claim(u64::from(length))?;
+        if options.collect_pictures {
+            let mut data = Vec::new();
+            file.by_ref().take(u64::from(length)).read_to_end(&mut data)?;
+            if data.len() as u64 != u64::from(length) {
+                return Err(Error::new(ErrorKind::UnexpectedEof, "picture data is shorter than its length claims").into());
+            }
+            data
+        } else {
+            skip_n(file.by_ref(), u64::from(length))?;
+            Vec::new()
+        }
+    };
+
+    Ok(Picture {
+        picture_type: picture_type.into(),
+        mime_type,
+        description,
+        width,
+        height,
+        color_depth,
+        colors_used,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::picture;
+
+    #[test]
+    fn length_past_the_block() {
+        let body = picture(3, "image/png", b"png");
+        // The block ends in the middle of the MIME type
+        match parse_picture(&mut &body[..], 12, &ParseOptions::new()) {
+            Err(FlacError::MalformedBlock { block_type: 6, declared_size: 12 }) => (),
+            other => panic!("expected MalformedBlock, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_picture_type() {
+        let body = picture(21, "image/png", b"png");
+        assert!(matches!(parse_picture(&mut &body[..], body.len() as u32, &ParseOptions::new()), Err(FlacError::InvalidPictureType(21))));
+    }
+}
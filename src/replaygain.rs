@@ -0,0 +1,34 @@
+/// The ReplayGain values of a track, used by players to normalize the loudness
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplayGain {
+    /// The gain of the track in dB
+    pub track_gain: Option<f32>,
+    /// The peak amplitude of the track, 1.0 is full scale
+    pub track_peak: Option<f32>,
+    /// The gain of the album in dB
+    pub album_gain: Option<f32>,
+    /// The peak amplitude of the album, 1.0 is full scale
+    pub album_peak: Option<f32>,
+}
+
+/// Parses a gain of the form `-6.48 dB`, the unit is optional
+pub fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = if value.to_ascii_lowercase().ends_with("db") {
+        &value[..value.len() - 2]
+    } else {
+        value
+    };
+    value.trim().parse().ok()
+}
+
+/// Parses an R128 gain, which is a Q7.8 fixed-point number like `-1024` for -4 dB
+pub fn parse_r128_gain(value: &str) -> Option<f32> {
+    value.trim().parse::<i16>().ok().map(|x| f32::from(x) / 256.0)
+}
+
+/// Parses a peak of the form `0.998`
+pub fn parse_peak(value: &str) -> Option<f32> {
+    value.trim().parse().ok()
+}
@@ -0,0 +1,43 @@
+use std::io::prelude::*;
+
+use byteorder::{ReadBytesExt, BigEndian};
+
+use crate::Result;
+
+/// The sample number that marks a seek point as a placeholder
+const PLACEHOLDER: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
+/// Represents a single seek point of a SEEKTABLE block
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SeekPoint {
+    /// The sample number of the first sample in the target frame
+    pub sample_number: u64,
+    /// The offset in bytes from the first byte of the first frame to the target frame
+    pub stream_offset: u64,
+    /// The number of samples in the target frame
+    pub frame_samples: u16,
+}
+
+impl SeekPoint {
+    /// Returns true if this seek point is a placeholder, which does not point to a frame
+    pub fn is_placeholder(&self) -> bool {
+        self.sample_number == PLACEHOLDER
+    }
+}
+
+/// Parses a SEEKTABLE block of the given length if the reader is positioned at the start of the block
+pub fn parse_seek_table<R>(file: &mut R, length: u32) -> Result<Vec<SeekPoint>>
+where R: Read {
+    // Every seek point takes up 18 bytes
+    let npoints = length / 18;
+    let mut points = Vec::with_capacity(npoints as usize);
+    for _ in 0..npoints {
+        points.push(SeekPoint {
+            sample_number: file.read_u64::<BigEndian>()?,
+            stream_offset: file.read_u64::<BigEndian>()?,
+            frame_samples: file.read_u16::<BigEndian>()?,
+        });
+    }
+    Ok(points)
+}
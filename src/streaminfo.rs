@@ -0,0 +1,285 @@
+use std::convert::TryFrom;
+use std::io::prelude::*;
+use std::time::Duration;
+
+use crate::Result;
+use crate::bits::BitReader;
+
+/// Represents the STREAMINFO block, which holds the basic properties of the audio stream
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StreamInfo {
+    /// The minimum block size (in samples) used in the stream
+    pub min_block_size: u16,
+    /// The maximum block size (in samples) used in the stream
+    pub max_block_size: u16,
+    /// The minimum frame size (in bytes) used in the stream, `None` if the encoder stored 0 for
+    /// unknown
+    pub min_frame_size: Option<u32>,
+    /// The maximum frame size (in bytes) used in the stream, `None` if the encoder stored 0 for
+    /// unknown
+    pub max_frame_size: Option<u32>,
+    /// The sample rate in Hz
+    pub sample_rate: u32,
+    /// The number of channels
+    pub channels: u8,
+    /// The number of bits per sample
+    pub bits_per_sample: u8,
+    /// The total number of samples per channel, 0 means the number is unknown
+    pub total_samples: u64,
+    /// The MD5 signature of the unencoded audio data
+    pub md5: [u8; 16],
+}
+
+/// Whether the STREAMINFO block has an MD5 signature to verify the audio against, as returned by
+/// `FlacParser::verify_md5`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Md5Status {
+    /// The signature is all zeros, the encoder did not compute it
+    Unknown,
+    /// A signature is stored, it has not been compared to the audio as decoding the audio frames
+    /// is not supported
+    Present,
+}
+
+/// The speaker layout of the channels, as assigned by FLAC for a number of channels
+///
+/// The channels of a frame are in the order listed for each layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+    /// A single channel
+    Mono,
+    /// Left and right
+    Stereo,
+    /// Left, right and center
+    Surround30,
+    /// Front left, front right, back left and back right
+    Quadraphonic,
+    /// Front left, front right, front center, back left and back right
+    Surround50,
+    /// Front left, front right, front center, LFE, back left and back right
+    Surround51,
+    /// Front left, front right, front center, LFE, back center, side left and side right
+    Surround61,
+    /// Front left, front right, front center, LFE, back left, back right, side left and side
+    /// right
+    Surround71,
+    /// A number of channels that FLAC can't store, only possible if `channels` was changed
+    Other(u8),
+}
+
+impl StreamInfo {
+    /// Get the duration of the stream in seconds, or `None` if the number of samples or the sample
+    /// rate is unknown
+    pub fn duration_seconds(&self) -> Option<f64> {
+        if self.total_samples == 0 || self.sample_rate == 0 {
+            return None;
+        }
+        Some(self.total_samples as f64 / f64::from(self.sample_rate))
+    }
+
+    /// Get the duration of the stream, or `None` if the number of samples or the sample rate is
+    /// unknown
+    ///
+    /// The duration is computed with integers, so it is exact to the nanosecond.
+    pub fn duration(&self) -> Option<Duration> {
+        if self.total_samples == 0 {
+            return None;
+        }
+        self.sample_to_time(self.total_samples)
+    }
+
+    /// Estimate the average bitrate of the audio in kbit/s, from the number of bytes of the audio
+    /// frames
+    ///
+    /// `audio_bytes` is not the length of the file: the metadata has to be subtracted first, e.g.
+    /// `file_len - parser.metadata_size()?`. Returns `None` if the number of samples or the sample
+    /// rate is unknown.
+    pub fn estimate_bitrate(&self, audio_bytes: u64) -> Option<u32> {
+        if self.total_samples == 0 || self.sample_rate == 0 {
+            return None;
+        }
+        // bits / (samples / rate) / 1000, in 128 bits so the multiplication can't overflow
+        let bitrate = u128::from(audio_bytes) * 8 * u128::from(self.sample_rate)
+            / u128::from(self.total_samples) / 1000;
+        u32::try_from(bitrate).ok()
+    }
+
+    /// Returns true if the stream stays within the limits of the FLAC subset, which every decoder
+    /// has to support
+    ///
+    /// Only the limits that can be checked with the STREAMINFO block are checked: a block size of
+    /// at most 16384, or 4608 if the sample rate is at most 48 kHz, and at most 24 bits per
+    /// sample. The subset also limits the encoding of the frames, which is not checked.
+    pub fn is_subset(&self) -> bool {
+        let max_block_size = if self.sample_rate <= 48_000 { 4608 } else { 16_384 };
+        self.max_block_size <= max_block_size && self.bits_per_sample <= 24
+    }
+
+    /// Get the speaker layout of the channels
+    pub fn channel_layout(&self) -> ChannelLayout {
+        match self.channels {
+            1 => ChannelLayout::Mono,
+            2 => ChannelLayout::Stereo,
+            3 => ChannelLayout::Surround30,
+            4 => ChannelLayout::Quadraphonic,
+            5 => ChannelLayout::Surround50,
+            6 => ChannelLayout::Surround51,
+            7 => ChannelLayout::Surround61,
+            8 => ChannelLayout::Surround71,
+            channels => ChannelLayout::Other(channels),
+        }
+    }
+
+    /// Get the number of bytes needed to store a single sample, e.g. 2 for 16 bit audio
+    pub fn bytes_per_sample(&self) -> u32 {
+        u32::from(self.bits_per_sample).div_ceil(8)
+    }
+
+    /// Get the duration of a frame with the given block size, or `None` if the sample rate is 0
+    pub fn frame_duration(&self, block_size: u16) -> Option<Duration> {
+        self.sample_to_time(u64::from(block_size))
+    }
+
+    /// Convert a sample number, like the ones in the seek table, to the time from the start of the
+    /// stream, or `None` if the sample rate is 0
+    pub fn sample_to_time(&self, sample: u64) -> Option<Duration> {
+        if self.sample_rate == 0 {
+            return None;
+        }
+        let sample_rate = u64::from(self.sample_rate);
+        let seconds = sample / sample_rate;
+        // Computing the nanoseconds of the remainder separately can't overflow
+        let nanos = sample % sample_rate * 1_000_000_000 / sample_rate;
+        Some(Duration::new(seconds, nanos as u32))
+    }
+
+    /// Returns true if the encoder stored an MD5 signature, an all-zero signature means unknown
+    ///
+    /// A stored signature enables integrity checking, by comparing it to the MD5 of the decoded
+    /// audio.
+    pub fn has_md5(&self) -> bool {
+        self.md5.iter().any(|&b| b != 0)
+    }
+
+    /// Get the MD5 signature as a lowercase hex string, or `None` if the signature is unknown
+    ///
+    /// Files with the same signature have identical audio, even if their metadata differs.
+    pub fn md5_hex(&self) -> Option<String> {
+        if !self.has_md5() {
+            return None;
+        }
+        Some(self.md5.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+/// Parses a STREAMINFO block if the reader is positioned at the start of the block
+pub fn parse_stream_info<R>(file: &mut R) -> Result<StreamInfo>
+where R: Read {
+    let mut bits = BitReader::new(file.by_ref());
+    let min_block_size = bits.read_bits(16)? as u16;
+    let max_block_size = bits.read_bits(16)? as u16;
+    // A frame size of 0 means that the encoder did not know it
+    let min_frame_size = Some(bits.read_bits(24)? as u32).filter(|&size| size != 0);
+    let max_frame_size = Some(bits.read_bits(24)? as u32).filter(|&size| size != 0);
+    // Sample rate, channels, bits per sample and total samples are packed into 64 bits
+    let sample_rate = bits.read_bits(20)? as u32;
+    let channels = bits.read_bits(3)? as u8 + 1;
+    let bits_per_sample = bits.read_bits(5)? as u8 + 1;
+    let total_samples = bits.read_bits(36)?;
+
+    let mut md5 = [0; 16];
+    file.read_exact(&mut md5)?;
+
+    Ok(StreamInfo {
+        min_block_size,
+        max_block_size,
+        min_frame_size,
+        max_frame_size,
+        sample_rate,
+        channels,
+        bits_per_sample,
+        total_samples,
+        md5,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::stream_info;
+
+    /// A STREAMINFO block with the given MD5 signature
+    fn block(md5: [u8; 16]) -> Vec<u8> {
+        let mut block = stream_info();
+        block[18..].copy_from_slice(&md5);
+        block
+    }
+
+    #[test]
+    fn all_zero_md5_is_unknown() {
+        let stream_info = parse_stream_info(&mut &block([0; 16])[..]).unwrap();
+        assert!(!stream_info.has_md5());
+        assert_eq!(stream_info.md5_hex(), None);
+    }
+
+    #[test]
+    fn zero_frame_sizes_are_unknown() {
+        let mut block = stream_info();
+        block[4..10].copy_from_slice(&[0; 6]);
+        let stream_info = parse_stream_info(&mut &block[..]).unwrap();
+        assert_eq!(stream_info.min_frame_size, None);
+        assert_eq!(stream_info.max_frame_size, None);
+    }
+
+    #[test]
+    fn stored_frame_sizes_are_known() {
+        let stream_info = parse_stream_info(&mut &stream_info()[..]).unwrap();
+        assert_eq!(stream_info.min_frame_size, Some(14));
+        assert_eq!(stream_info.max_frame_size, Some(0x3A00));
+    }
+
+    #[test]
+    fn stored_md5_is_present() {
+        let mut md5 = [0; 16];
+        md5[15] = 0xAB;
+        let stream_info = parse_stream_info(&mut &block(md5)[..]).unwrap();
+        assert!(stream_info.has_md5());
+        assert_eq!(stream_info.md5_hex().unwrap(), "000000000000000000000000000000ab");
+    }
+
+    #[test]
+    fn short_block() {
+        assert!(parse_stream_info(&mut &stream_info()[..10]).is_err());
+    }
+
+    #[test]
+    fn duration_of_a_known_stream() {
+        let stream_info = parse_stream_info(&mut &stream_info()[..]).unwrap();
+        assert_eq!(stream_info.duration_seconds(), Some(10.0));
+        assert_eq!(stream_info.duration(), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn duration_with_unknown_sample_rate() {
+        let mut stream_info = parse_stream_info(&mut &stream_info()[..]).unwrap();
+        stream_info.sample_rate = 0;
+        assert_eq!(stream_info.duration_seconds(), None);
+        assert_eq!(stream_info.duration(), None);
+        stream_info.sample_rate = 44_100;
+        stream_info.total_samples = 0;
+        assert_eq!(stream_info.duration_seconds(), None);
+    }
+
+
+    #[test]
+    fn bitrate_of_cd_audio() {
+        let mut stream_info = parse_stream_info(&mut &stream_info()[..]).unwrap();
+        // 10 seconds of uncompressed CD audio is 1411.2 kbit/s, a typical FLAC file is about 60%
+        assert_eq!(stream_info.estimate_bitrate(1_764_000), Some(1411));
+        assert_eq!(stream_info.estimate_bitrate(1_058_400), Some(846));
+        assert_eq!(stream_info.estimate_bitrate(0), Some(0));
+        stream_info.total_samples = 0;
+        assert_eq!(stream_info.estimate_bitrate(1_058_400), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Builders for the byte buffers that the tests parse
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::bytes::encode_vorbis_comment;
+
+/// Encodes a metadata block with its header
+pub fn block(block_type: u8, is_last: bool, body: &[u8]) -> Vec<u8> {
+    let length = body.len() as u32;
+    let mut block = vec![block_type | if is_last { 0x80 } else { 0 }, (length >> 16) as u8, (length >> 8) as u8, length as u8];
+    block.extend_from_slice(body);
+    block
+}
+
+/// Joins the `fLaC` marker and the blocks into a file
+pub fn flac(blocks: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = b"fLaC".to_vec();
+    for block in blocks {
+        file.extend_from_slice(block);
+    }
+    file
+}
+
+/// The body of a STREAMINFO block of 44.1 kHz stereo at 16 bits, with 4096 samples per block,
+/// 441000 samples in total and an unknown MD5 signature
+pub fn stream_info() -> Vec<u8> {
+    let mut body = vec![0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x0E, 0x00, 0x3A, 0x00];
+    // Sample rate, channels, bits per sample and total samples
+    body.extend_from_slice(&[0x0A, 0xC4, 0x42, 0xF0, 0x00, 0x06, 0xBA, 0xA8]);
+    body.extend_from_slice(&[0; 16]);
+    body
+}
+
+/// Encodes the body of a VORBIS_COMMENT block with the vendor string `test`
+pub fn comments(fields: &[(&str, &str)]) -> Vec<u8> {
+    let fields: Vec<(String, String)> = fields.iter().map(|&(key, value)| (key.into(), value.into())).collect();
+    encode_vorbis_comment("test", &fields)
+}
+
+/// Encodes the body of a VORBIS_COMMENT block with the vendor string `test` from raw parts, so
+/// the lengths and the comment count can be wrong
+pub fn raw_comments(vendor_length: u32, count: u32, comments: &[&[u8]]) -> Vec<u8> {
+    let mut body = vendor_length.to_le_bytes().to_vec();
+    body.extend_from_slice(b"test");
+    body.extend_from_slice(&count.to_le_bytes());
+    for comment in comments {
+        body.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        body.extend_from_slice(comment);
+    }
+    body
+}
+
+/// Encodes the body of a PICTURE block without a description or dimensions
+#[cfg(feature = "std")]
+pub fn picture(picture_type: u32, mime_type: &str, data: &[u8]) -> Vec<u8> {
+    let mut body = picture_type.to_be_bytes().to_vec();
+    body.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    body.extend_from_slice(mime_type.as_bytes());
+    body.extend_from_slice(&[0; 4 + 16]);
+    body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    body.extend_from_slice(data);
+    body
+}
+
+/// Encodes an Ogg page with the given lacing values and data, the checksum is left at 0
+#[cfg(feature = "std")]
+pub fn ogg_page(lacing: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut page = b"OggS".to_vec();
+    // Version, header type, granule position, serial number, sequence number and checksum
+    page.extend_from_slice(&[0; 22]);
+    page.push(lacing.len() as u8);
+    page.extend_from_slice(lacing);
+    page.extend_from_slice(data);
+    page
+}
+
+/// Encodes the packets into a single Ogg page
+#[cfg(feature = "std")]
+pub fn ogg_packets(packets: &[&[u8]]) -> Vec<u8> {
+    let mut lacing = Vec::new();
+    let mut data = Vec::new();
+    for packet in packets {
+        // A packet is split into segments of 255 bytes, the shorter last segment ends it
+        lacing.extend(core::iter::repeat_n(255, packet.len() / 255));
+        lacing.push((packet.len() % 255) as u8);
+        data.extend_from_slice(packet);
+    }
+    ogg_page(&lacing, &data)
+}
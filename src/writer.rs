@@ -0,0 +1,331 @@
+use std::cmp;
+use std::io::SeekFrom;
+use std::io::prelude::*;
+
+use byteorder::{WriteBytesExt, BigEndian};
+
+use crate::{FlacError, ParseOptions, Result, VorbisMetadata};
+use crate::bytes::encode_vorbis_comment;
+use crate::parser::{is_flac_file, parse_vorbis_comments, read_block_header};
+use crate::picture::Picture;
+
+/// The largest body a metadata block can have, the length in the header is 24 bits
+const MAX_BLOCK_LENGTH: u64 = 0xFF_FFFF;
+
+/// The amount of padding that is added when the audio frames have to be moved anyway
+const DEFAULT_PADDING: u64 = 8192;
+
+/// The vendor string used when a file did not have a comment block yet
+const VENDOR: &str = concat!("flacparse ", env!("CARGO_PKG_VERSION"));
+
+/// Updates the Vorbis comments of a FLAC file in place
+///
+/// The new comment block replaces the old one and reuses the available PADDING blocks. Only if
+/// the new metadata does not fit in the space of the old metadata, the audio frames are moved
+/// towards the end of the file to make room.
+pub struct FlacWriter<'a, F>
+where F: 'a + Read + Write + Seek {
+    file: &'a mut F,
+    /// The type and body of every metadata block, except VORBIS_COMMENT and PADDING blocks
+    blocks: Vec<(u8, Vec<u8>)>,
+    /// The index in `blocks` at which the comment block is written
+    comment_position: usize,
+    comments: VorbisMetadata,
+    /// The offset of the first audio frame
+    audio_offset: u64,
+}
+
+impl<'a, F> FlacWriter<'a, F>
+where F: Read + Write + Seek {
+    /// Reads the metadata of the file, the file is not changed until `write` is called
+    pub fn new(file: &'a mut F) -> Result<FlacWriter<'a, F>> {
+        let file_length = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+        if !is_flac_file(&mut *file)? {
+            return Err(FlacError::NotFlac);
+        }
+
+        let mut blocks = Vec::new();
+        let mut comments = None;
+        // A comment block is put right after STREAMINFO if the file has none
+        let mut comment_position = 1;
+        loop {
+            let header = read_block_header(&mut *file)?;
+            // Check the length before allocating the body, a corrupt header can claim up to 16 MiB
+            if u64::from(header.length) > file_length - file.stream_position()? {
+                return Err(FlacError::MalformedBlock { block_type: header.block_type, declared_size: header.length });
+            }
+            let mut body = vec![0; header.length as usize];
+            file.read_exact(&mut body)?;
+            match header.block_type {
+                1 => (),
+                4 => {
+                    comments = Some(parse_vorbis_comments(&mut &body[..], &ParseOptions::new())?);
+                    comment_position = blocks.len();
+                },
+                block_type => blocks.push((block_type, body)),
+            }
+            if header.is_last {
+                break;
+            }
+        }
+        let audio_offset = file.stream_position()?;
+
+        let comments = comments.unwrap_or_else(|| VorbisMetadata {
+            vendor_string: VENDOR.to_string(),
+            user_comments: Default::default(),
+            order: Vec::new(),
+            warnings: Vec::new(),
+        });
+        let comment_position = cmp::min(comment_position, blocks.len());
+        Ok(FlacWriter { file, blocks, comment_position, comments, audio_offset })
+    }
+
+    /// Set a field to a single value, replacing all existing values of the field
+    pub fn set_comment<K, V>(&mut self, key: K, value: V)
+    where K: AsRef<str>, V: Into<String> {
+        self.comments.set_all(key.as_ref(), vec![value.into()]);
+    }
+
+    /// Remove all values of a field, field names are case-insensitive
+    pub fn remove_comment(&mut self, key: &str) {
+        self.comments.set_all(key, Vec::new());
+    }
+
+    /// Add a PICTURE block, e.g. to embed cover art
+    ///
+    /// The picture is written after the existing blocks. If the picture is larger than the
+    /// available padding, `write` moves the audio frames to make room.
+    pub fn add_picture(&mut self, picture: Picture) {
+        self.blocks.push((6, encode_picture(&picture)));
+    }
+
+    /// Write the metadata to the file
+    pub fn write(&mut self) -> Result<()> {
+        let comments: Vec<(String, String)> = self.comments.iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        let comment_block = encode_vorbis_comment(&self.comments.vendor_string, &comments);
+        if comment_block.len() as u64 > MAX_BLOCK_LENGTH {
+            return Err(FlacError::BlockTooLarge { block_type: 4, size: comment_block.len() as u64 });
+        }
+        if let Some((block_type, body)) = self.blocks.iter().find(|(_, body)| body.len() as u64 > MAX_BLOCK_LENGTH) {
+            return Err(FlacError::BlockTooLarge { block_type: *block_type, size: body.len() as u64 });
+        }
+        let size = self.blocks.iter().map(|(_, b)| 4 + b.len() as u64).sum::<u64>() + 4 + comment_block.len() as u64;
+
+        // The space between the fLaC marker and the first audio frame
+        let available = self.audio_offset - 4;
+        let padding = if size == available || size + 4 <= available {
+            available - size
+        } else {
+            // The new metadata does not fit, so the audio frames have to be moved
+            let padding = 4 + DEFAULT_PADDING;
+            self.move_audio(size + padding - available)?;
+            padding
+        };
+
+        let mut blocks: Vec<(u8, &[u8])> = self.blocks.iter().map(|(t, b)| (*t, &b[..])).collect();
+        blocks.insert(self.comment_position, (4, &comment_block));
+        let padding_blocks = padding_lengths(padding);
+        let nblocks = blocks.len() + padding_blocks.len();
+        let mut metadata = Vec::with_capacity((size + padding) as usize);
+        for (i, &(block_type, body)) in blocks.iter().enumerate() {
+            write_block_header(&mut metadata, block_type, body.len() as u32, i + 1 == nblocks);
+            metadata.extend_from_slice(body);
+        }
+        for (i, &length) in padding_blocks.iter().enumerate() {
+            write_block_header(&mut metadata, 1, length, blocks.len() + i + 1 == nblocks);
+            metadata.resize(metadata.len() + length as usize, 0);
+        }
+
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&metadata)?;
+        self.file.flush()?;
+        self.audio_offset = 4 + size + padding;
+        Ok(())
+    }
+
+    /// Move the audio frames `distance` bytes towards the end of the file
+    fn move_audio(&mut self, distance: u64) -> Result<()> {
+        let mut buf = vec![0; 64 * 1024];
+        let mut position = self.file.seek(SeekFrom::End(0))?;
+        // Copy from the end of the file, so no data is overwritten before it is moved
+        while position > self.audio_offset {
+            let n = cmp::min(buf.len() as u64, position - self.audio_offset);
+            position -= n;
+            self.file.seek(SeekFrom::Start(position))?;
+            self.file.read_exact(&mut buf[..n as usize])?;
+            self.file.seek(SeekFrom::Start(position + distance))?;
+            self.file.write_all(&buf[..n as usize])?;
+        }
+        self.audio_offset += distance;
+        Ok(())
+    }
+}
+
+/// Encodes a picture into the body of a PICTURE block
+fn encode_picture(picture: &Picture) -> Vec<u8> {
+    let mut block = Vec::new();
+    // Writing to a Vec can't fail
+    block.write_u32::<BigEndian>(picture.picture_type.into()).unwrap();
+    block.write_u32::<BigEndian>(picture.mime_type.len() as u32).unwrap();
+    block.extend_from_slice(picture.mime_type.as_bytes());
+    block.write_u32::<BigEndian>(picture.description.len() as u32).unwrap();
+    block.extend_from_slice(picture.description.as_bytes());
+    block.write_u32::<BigEndian>(picture.width).unwrap();
+    block.write_u32::<BigEndian>(picture.height).unwrap();
+    block.write_u32::<BigEndian>(picture.color_depth).unwrap();
+    block.write_u32::<BigEndian>(picture.colors_used).unwrap();
+    block.write_u32::<BigEndian>(picture.data.len() as u32).unwrap();
+    block.extend_from_slice(&picture.data);
+    block
+}
+
+/// Appends a metadata block header to the buffer
+fn write_block_header(buf: &mut Vec<u8>, block_type: u8, length: u32, is_last: bool) {
+    let last = if is_last { 0b1000_0000 } else { 0 };
+    // Writing to a Vec can't fail
+    buf.write_u8(block_type | last).unwrap();
+    buf.write_u24::<BigEndian>(length).unwrap();
+}
+
+/// Splits `size` bytes into the body lengths of PADDING blocks that exactly fill it
+fn padding_lengths(size: u64) -> Vec<u32> {
+    let mut lengths = Vec::new();
+    let mut remaining = size;
+    while remaining > 0 {
+        let mut length = cmp::min(remaining - 4, MAX_BLOCK_LENGTH);
+        // Never leave a gap that is too small for another block header
+        let rest = remaining - 4 - length;
+        if rest > 0 && rest < 4 {
+            length -= 4;
+        }
+        lengths.push(length as u32);
+        remaining -= 4 + length;
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::{parse_slice, BlockEntry, FlacParser, MusicData, PictureType};
+    use crate::test_util::{block, comments, flac, stream_info};
+
+    /// Stands in for the audio frames, starting with a frame sync code
+    const AUDIO: &[u8] = &[0xFF, 0xF8, 1, 2, 3, 4, 5, 6, 7, 8];
+
+    /// A file with a STREAMINFO block, a comment block with the title and a PADDING block
+    fn file(title: &str, padding: usize) -> Cursor<Vec<u8>> {
+        let mut file = flac(&[block(0, false, &stream_info()), block(4, false, &comments(&[("TITLE", title)])), block(1, true, &vec![0; padding])]);
+        file.extend_from_slice(AUDIO);
+        Cursor::new(file)
+    }
+
+    /// Sets the title and writes the file, returns the new block layout
+    fn rewrite(file: &mut Cursor<Vec<u8>>, title: &str) -> Vec<BlockEntry> {
+        {
+            let mut writer = FlacWriter::new(file).unwrap();
+            writer.set_comment("TITLE", title);
+            writer.write().unwrap();
+        }
+        let data = file.get_ref();
+        let layout = FlacParser::new(&data[..]).unwrap().block_layout().unwrap();
+        let flags: Vec<bool> = layout.iter().map(|entry| entry.is_last).collect();
+        assert_eq!(flags.iter().filter(|&&last| last).count(), 1);
+        assert_eq!(flags.last(), Some(&true));
+        let last = layout.last().unwrap();
+        let audio_offset = (last.offset + 4 + u64::from(last.length)) as usize;
+        assert_eq!(&data[audio_offset..], AUDIO);
+        assert_eq!(parse_slice(data).unwrap().title(), Some(title));
+        layout
+    }
+
+    fn types(layout: &[BlockEntry]) -> Vec<u8> {
+        layout.iter().map(|entry| entry.block_type).collect()
+    }
+
+    #[test]
+    fn shrinking_grows_the_padding() {
+        let mut file = file("a long title", 20);
+        let length = file.get_ref().len();
+        let layout = rewrite(&mut file, "short");
+        assert_eq!(types(&layout), [0, 4, 1]);
+        assert_eq!(layout[2].length, 20 + 7);
+        assert_eq!(file.get_ref().len(), length);
+    }
+
+    #[test]
+    fn exact_fit_replaces_the_padding() {
+        let mut file = file("a", 20);
+        let length = file.get_ref().len();
+        // Grows the comment block by the padding and its header
+        let layout = rewrite(&mut file, &"a".repeat(25));
+        assert_eq!(types(&layout), [0, 4]);
+        assert_eq!(file.get_ref().len(), length);
+    }
+
+    #[test]
+    fn growing_past_the_padding_moves_the_audio() {
+        let mut file = file("a", 20);
+        let length = file.get_ref().len();
+        let layout = rewrite(&mut file, &"a".repeat(100));
+        assert_eq!(types(&layout), [0, 4, 1]);
+        assert_eq!(u64::from(layout[2].length), DEFAULT_PADDING);
+        assert!(file.get_ref().len() > length);
+    }
+
+
+    #[test]
+    fn added_picture_reads_back() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        let mut file = file("a", 20);
+        {
+            let mut writer = FlacWriter::new(&mut file).unwrap();
+            writer.add_picture(Picture {
+                picture_type: PictureType::FrontCover,
+                mime_type: "image/png".into(),
+                description: "Cover".into(),
+                width: 1,
+                height: 1,
+                color_depth: 24,
+                colors_used: 0,
+                data: png.clone(),
+            });
+            writer.write().unwrap();
+        }
+        let data = file.get_ref();
+        assert!(data.ends_with(AUDIO));
+        let pictures = FlacParser::new(&data[..]).unwrap().pictures().unwrap();
+        assert_eq!(pictures.len(), 1);
+        assert_eq!(pictures[0].picture_type, PictureType::FrontCover);
+        assert_eq!(pictures[0].mime_type, "image/png");
+        assert_eq!(pictures[0].description, "Cover");
+        assert_eq!((pictures[0].width, pictures[0].height, pictures[0].color_depth), (1, 1, 24));
+        assert_eq!(pictures[0].data, png);
+        assert_eq!(parse_slice(data).unwrap().title(), Some("a"));
+    }
+
+    #[test]
+    fn remove_comment_removes_every_value() {
+        let mut file = flac(&[
+            block(0, false, &stream_info()),
+            block(4, false, &comments(&[("ARTIST", "a"), ("TITLE", "t"), ("artist", "b"), ("ALBUM", "x")])),
+            block(1, true, &[0; 20]),
+        ]);
+        file.extend_from_slice(AUDIO);
+        let mut file = Cursor::new(file);
+        {
+            let mut writer = FlacWriter::new(&mut file).unwrap();
+            writer.remove_comment("Artist");
+            writer.write().unwrap();
+        }
+        let comments = FlacParser::new(&file.get_ref()[..]).unwrap().comments().unwrap().unwrap();
+        assert!(!comments.contains("ARTIST"));
+        assert_eq!(comments.get_all("TITLE"), ["t"]);
+        assert_eq!(comments.get_all("ALBUM"), ["x"]);
+        assert_eq!(comments.len(), 2);
+    }
+}